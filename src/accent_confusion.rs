@@ -0,0 +1,262 @@
+/// Diacritic confusion model, many to many, with a cost for each change.
+///
+/// The old table of map_accents() could only map each char to one other
+/// char ('e' to 'é' but never to 'ê') and change only one position. Here
+/// each char belongs to groups of chars that can be confused with each
+/// other, and a word can change in several positions while the total cost
+/// is not greater then the max cost. Ex: with max cost 2
+///
+///     nao -> não, náo, nâo, ...
+///     voo -> vôo, vóo, vôó, ...
+///
+/// The model is loaded from a text file:
+///
+///     # Comment
+///     max_cost 2
+///     <cost> <char> <char> ...
+///
+/// where every char in a line can be confused with any other char in the
+/// same line, with that cost. Without the "max_cost" line the max cost is
+/// DEFAULT_MAX_COST, the same of the default model.
+///
+
+use std::fs;
+use std::collections::HashMap;
+
+use crate::strings_extender::{StringUtils, StringUtilsVecChars};
+
+// The max cost of the default model and of a file without "max_cost".
+pub const DEFAULT_MAX_COST: u32 = 2;
+
+#[derive(Debug, Clone)]
+pub struct AccentConfusion {
+    // For each char, the chars it can be confused with and the cost.
+    confusions: HashMap<char, Vec<(char, u32)>>,
+    pub max_cost: u32,
+}
+
+impl AccentConfusion {
+    pub fn new(max_cost: u32) -> Self {
+        AccentConfusion { confusions: HashMap::new(), max_cost }
+    }
+
+    // The Portuguese diacritics, with the default max cost.
+    pub fn default_confusion() -> Self {
+        let mut accent_confusion = AccentConfusion::new(DEFAULT_MAX_COST);
+        let groups = [
+            "aáàâã", "eéê", "ií", "oóôõ", "uúü", "cç",
+            "AÁÀÂÃ", "EÉÊ", "IÍ", "OÓÔÕ", "UÚÜ", "CÇ",
+        ];
+        for group in groups {
+            accent_confusion.add_group(&group.get_vec_chars(), 1);
+        }
+        accent_confusion
+    }
+
+    // Every char in the group can be confused with any other char in it.
+    pub fn add_group(&mut self, group: &[char], cost: u32) {
+        for from in group {
+            for to in group {
+                if from == to {
+                    continue;
+                }
+                let alternatives = self.confusions.entry(*from).or_default();
+                match alternatives.iter_mut().find(|(ch, _)| ch == to) {
+                    Some(alt) => alt.1 = alt.1.min(cost),
+                    None => alternatives.push((*to, cost)),
+                }
+            }
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut accent_confusion = AccentConfusion::new(DEFAULT_MAX_COST);
+        for (line_num, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let err = |msg: &str| format!("line {}: {}", line_num + 1, msg);
+            if tokens[0] == "max_cost" {
+                let max_cost = tokens.get(1).and_then(|t| t.parse::<u32>().ok())
+                    .ok_or_else(|| err("max_cost needs a number"))?;
+                accent_confusion.max_cost = max_cost;
+                continue;
+            }
+            let cost = tokens[0].parse::<u32>()
+                .map_err(|_| err("the line must start with the cost"))?;
+            let mut group = Vec::new();
+            for token in &tokens[1..] {
+                let chars = token.get_vec_chars();
+                if chars.len() != 1 {
+                    return Err(err(&format!("\"{}\" isn't a single char", token)));
+                }
+                group.push(chars[0]);
+            }
+            accent_confusion.add_group(&group, cost);
+        }
+        Ok(accent_confusion)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("{}: {}", path, err))?;
+        AccentConfusion::parse(&text)
+            .map_err(|err| format!("{}: {}", path, err))
+    }
+
+    pub fn alternatives(&self, ch: char) -> &[(char, u32)] {
+        match self.confusions.get(&ch) {
+            Some(alternatives) => alternatives,
+            None => &[],
+        }
+    }
+
+    // Iterator over all the variants of the word with cost in
+    // [1, max_cost], the word itself isn't included.
+    pub fn variants_iter(&self, word: &str) -> AccentVariants<'_> {
+        let original = word.get_vec_chars();
+        AccentVariants {
+            accent_confusion: self,
+            current: original.clone(),
+            original,
+            stack: vec![VariantFrame { pos: 0, alt_index: 0, cost: 0, changed_pos: None }],
+            tmp_string: String::with_capacity(word.len() + 8),
+        }
+    }
+
+    // All the variants of the word, sorted by cost and then by the word.
+    pub fn variants(&self, word: &str) -> Vec<(String, u32)> {
+        let mut res: Vec<(String, u32)> = self.variants_iter(word).collect();
+        res.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        res
+    }
+}
+
+// Depth first generation of the variants. Each frame of the stack chooses
+// the next change in the positions >= pos, with the cost of the changes
+// made by the frames below it. The change that created a frame is undone
+// when the frame is popped, so each variant is generated only once.
+struct VariantFrame {
+    pos: usize,
+    alt_index: usize,
+    cost: u32,
+    changed_pos: Option<usize>,
+}
+
+pub struct AccentVariants<'a> {
+    accent_confusion: &'a AccentConfusion,
+    original: Vec<char>,
+    current: Vec<char>,
+    stack: Vec<VariantFrame>,
+    tmp_string: String,
+}
+
+impl<'a> Iterator for AccentVariants<'a> {
+    type Item = (String, u32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let frame = self.stack.last()?;
+            let (mut pos, mut alt_index, cost) = (frame.pos, frame.alt_index, frame.cost);
+
+            // Finds the next alternative that fits in the max cost.
+            let mut found: Option<(char, u32)> = None;
+            while pos < self.original.len() {
+                let alternatives = self.accent_confusion.alternatives(self.original[pos]);
+                while alt_index < alternatives.len() {
+                    let (alt_ch, alt_cost) = alternatives[alt_index];
+                    alt_index += 1;
+                    if cost + alt_cost <= self.accent_confusion.max_cost {
+                        found = Some((alt_ch, alt_cost));
+                        break;
+                    }
+                }
+                if found.is_some() {
+                    break;
+                }
+                pos += 1;
+                alt_index = 0;
+            }
+
+            match found {
+                Some((alt_ch, alt_cost)) => {
+                    // Remembers where to continue in this frame.
+                    let top = self.stack.len() - 1;
+                    self.stack[top].pos = pos;
+                    self.stack[top].alt_index = alt_index;
+
+                    self.current[pos] = alt_ch;
+                    let variant = self.current.to_string_buf(&mut self.tmp_string).clone();
+                    self.stack.push(VariantFrame { pos: pos + 1, alt_index: 0, cost: cost + alt_cost, changed_pos: Some(pos) });
+                    return Some((variant, cost + alt_cost));
+                },
+                None => {
+                    let frame = self.stack.pop()?;
+                    if let Some(changed_pos) = frame.changed_pos {
+                        self.current[changed_pos] = self.original[changed_pos];
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(variants: &[(String, u32)]) -> Vec<&str> {
+        variants.iter().map(|(word, _)| word.as_str()).collect()
+    }
+
+    #[test]
+    fn cost_is_bounded_by_max_cost() {
+        let mut accent_confusion = AccentConfusion::parse("1 a á\n2 e ê\n").unwrap();
+        assert_eq!(accent_confusion.max_cost, DEFAULT_MAX_COST);
+        assert_eq!(accent_confusion.variants("ae"),
+                   vec![("áe".to_string(), 1), ("aê".to_string(), 2)]);
+        accent_confusion.max_cost = 3;
+        assert_eq!(accent_confusion.variants("ae"),
+                   vec![("áe".to_string(), 1), ("aê".to_string(), 2), ("áê".to_string(), 3)]);
+        accent_confusion.max_cost = 0;
+        assert!(accent_confusion.variants("ae").is_empty());
+    }
+
+    #[test]
+    fn changes_several_positions() {
+        let accent_confusion = AccentConfusion::default_confusion();
+        let variants = accent_confusion.variants("nao");
+        assert!(variants.iter().all(|(_, cost)| (1..=DEFAULT_MAX_COST).contains(cost)));
+        let variants = words(&variants);
+        assert!(variants.contains(&"não"));
+        assert!(variants.contains(&"nàó"));
+        assert!(!variants.contains(&"nao"));
+        // 4 changes of 'a', 3 of 'o' and 4 * 3 of both.
+        assert_eq!(variants.len(), 4 + 3 + 4 * 3);
+        assert!(accent_confusion.variants("xyz").is_empty());
+    }
+
+    #[test]
+    fn each_variant_only_once() {
+        // The group is added twice, with different costs, the lower wins.
+        let mut accent_confusion = AccentConfusion::new(2);
+        accent_confusion.add_group(&['o', 'ô'], 2);
+        accent_confusion.add_group(&['o', 'ô'], 1);
+        let variants: Vec<(String, u32)> = accent_confusion.variants_iter("voo").collect();
+        let mut unique = words(&variants);
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), variants.len());
+        assert_eq!(accent_confusion.variants("voo"),
+                   vec![("voô".to_string(), 1), ("vôo".to_string(), 1), ("vôô".to_string(), 2)]);
+    }
+
+    #[test]
+    fn parse_errors_have_the_line() {
+        assert_eq!(AccentConfusion::parse("# c\nmax_cost x\n").unwrap_err(), "line 2: max_cost needs a number");
+        assert!(AccentConfusion::parse("a á\n").is_err());
+        assert!(AccentConfusion::parse("1 a áá\n").is_err());
+    }
+}
@@ -0,0 +1,465 @@
+/// Native reader of the HunSpell dictionaries, the affix file (.aff) and
+/// the stems file (.dic), without the C library.
+///
+/// Each stem of the .dic file has affix flags, ex: "casa/p", and each flag
+/// of the .aff file is a set of rules that strip and add chars at the end
+/// (SFX) or at the start (PFX) of the stem when a condition holds:
+///
+///     SFX p Y 2
+///     SFX p 0 s [^s]        casa -> casas
+///     SFX p ão ões ão       ação -> ações
+///
+/// The expansion applies the suffixes, the prefixes that combine with them
+/// (cross product) and the continuation flags of the rules, and gives the
+/// full list of the valid inflected forms. The check() looks the word up
+/// in that list, also in lower case for the Title and UPPER case words.
+///
+/// Supported: FLAG (char, long, num and UTF-8), AF aliases, PFX, SFX with
+/// continuation flags, NEEDAFFIX, FORBIDDENWORD and ONLYINCOMPOUND. The
+/// compounding rules aren't.
+///
+
+use std::fs;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagMode {
+    Char,
+    Long,
+    Num,
+}
+
+impl FlagMode {
+    pub fn parse_flags(&self, text: &str) -> Vec<String> {
+        match self {
+            FlagMode::Char => text.chars().map(|ch| ch.to_string()).collect(),
+            FlagMode::Long => {
+                let chars: Vec<char> = text.chars().collect();
+                chars.chunks(2).map(|pair| pair.iter().collect()).collect()
+            },
+            FlagMode::Num => text.split(',')
+                .map(|flag| flag.trim().to_string())
+                .filter(|flag| !flag.is_empty())
+                .collect(),
+        }
+    }
+}
+
+// One char of the condition of a rule.
+#[derive(Debug, Clone, PartialEq)]
+enum CondChar {
+    Any,
+    Char(char),
+    OneOf(Vec<char>),
+    NoneOf(Vec<char>),
+}
+
+impl CondChar {
+    fn matches(&self, ch: char) -> bool {
+        match self {
+            CondChar::Any => true,
+            CondChar::Char(cond_ch) => *cond_ch == ch,
+            CondChar::OneOf(chars) => chars.contains(&ch),
+            CondChar::NoneOf(chars) => !chars.contains(&ch),
+        }
+    }
+}
+
+fn parse_condition(text: &str) -> Vec<CondChar> {
+    let mut res = Vec::new();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '.' => res.push(CondChar::Any),
+            '[' => {
+                let mut group: Vec<char> = Vec::new();
+                let mut flag_negated = false;
+                for group_ch in chars.by_ref() {
+                    match group_ch {
+                        ']' => break,
+                        '^' if group.is_empty() && !flag_negated => flag_negated = true,
+                        _ => group.push(group_ch),
+                    }
+                }
+                if flag_negated {
+                    res.push(CondChar::NoneOf(group));
+                } else {
+                    res.push(CondChar::OneOf(group));
+                }
+            },
+            _ => res.push(CondChar::Char(ch)),
+        }
+    }
+    res
+}
+
+#[derive(Debug, Clone)]
+pub struct AffixRule {
+    pub strip: String,
+    pub add: String,
+    pub cont_flags: Vec<String>,
+    condition: Vec<CondChar>,
+}
+
+impl AffixRule {
+    // The condition is matched at the end of the word for the suffixes and
+    // at the start for the prefixes.
+    fn matches(&self, word: &str, flag_suffix: bool) -> bool {
+        let chars: Vec<char> = word.chars().collect();
+        if chars.len() < self.condition.len() {
+            return false;
+        }
+        let offset = if flag_suffix { chars.len() - self.condition.len() } else { 0 };
+        let flag_cond = self.condition.iter().enumerate()
+            .all(|(i, cond)| cond.matches(chars[offset + i]));
+        if flag_suffix {
+            flag_cond && word.ends_with(&self.strip)
+        } else {
+            flag_cond && word.starts_with(&self.strip)
+        }
+    }
+
+    fn apply(&self, word: &str, flag_suffix: bool) -> Option<String> {
+        if !self.matches(word, flag_suffix) {
+            return None;
+        }
+        if flag_suffix {
+            let base = &word[..word.len() - self.strip.len()];
+            // A rule can't remove the whole word.
+            if base.is_empty() && self.add.is_empty() {
+                return None;
+            }
+            Some(base.to_string() + &self.add)
+        } else {
+            let base = &word[self.strip.len()..];
+            if base.is_empty() && self.add.is_empty() {
+                return None;
+            }
+            Some(self.add.clone() + base)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AffixClass {
+    pub flag_suffix: bool,
+    pub cross_product: bool,
+    pub rules: Vec<AffixRule>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AffixFile {
+    pub flag_mode: FlagMode,
+    // The AF aliases, the index 1 is the first one.
+    pub flag_aliases: Vec<Vec<String>>,
+    pub classes: HashMap<String, AffixClass>,
+    pub need_affix: Option<String>,
+    pub forbidden_word: Option<String>,
+    pub only_in_compound: Option<String>,
+}
+
+impl AffixFile {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut affix_file = AffixFile {
+            flag_mode: FlagMode::Char,
+            flag_aliases: Vec::new(),
+            classes: HashMap::new(),
+            need_affix: None,
+            forbidden_word: None,
+            only_in_compound: None,
+        };
+
+        let mut flag_af_header = false;
+        for (num_line, line) in text.lines().enumerate() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.is_empty() || tokens[0].starts_with('#') {
+                continue;
+            }
+            let error = || format!("line {}: {}", num_line + 1, line);
+            match tokens[0] {
+                "FLAG" => {
+                    affix_file.flag_mode = match tokens.get(1).copied() {
+                        Some("long") => FlagMode::Long,
+                        Some("num") => FlagMode::Num,
+                        // "UTF-8" flags are chars.
+                        _ => FlagMode::Char,
+                    };
+                },
+                // The first AF line has the number of aliases.
+                "AF" if tokens.len() > 1 => {
+                    if flag_af_header {
+                        affix_file.flag_aliases.push(affix_file.flag_mode.parse_flags(tokens[1]));
+                    }
+                    flag_af_header = true;
+                },
+                "NEEDAFFIX" => affix_file.need_affix = tokens.get(1).map(|flag| flag.to_string()),
+                "FORBIDDENWORD" => affix_file.forbidden_word = tokens.get(1).map(|flag| flag.to_string()),
+                "ONLYINCOMPOUND" => affix_file.only_in_compound = tokens.get(1).map(|flag| flag.to_string()),
+                "PFX" | "SFX" => {
+                    let flag_suffix = tokens[0] == "SFX";
+                    if tokens.len() == 4 && !affix_file.classes.contains_key(tokens[1]) {
+                        // Header: "SFX flag cross_product num_rules".
+                        affix_file.classes.insert(tokens[1].to_string(), AffixClass {
+                            flag_suffix,
+                            cross_product: tokens[2] == "Y",
+                            rules: Vec::new(),
+                        });
+                        continue;
+                    }
+                    if tokens.len() < 4 {
+                        return Err(error());
+                    }
+                    // Rule: "SFX flag strip add[/cont_flags] [condition] [morph]".
+                    let (add, cont_flags) = match tokens[3].split_once('/') {
+                        Some((add, flags)) => (add, affix_file.resolve_flags(flags)),
+                        None => (tokens[3], Vec::new()),
+                    };
+                    let rule = AffixRule {
+                        strip: if tokens[2] == "0" { String::new() } else { tokens[2].to_string() },
+                        add: if add == "0" { String::new() } else { add.to_string() },
+                        cont_flags,
+                        condition: parse_condition(tokens.get(4).copied().unwrap_or(".")),
+                    };
+                    match affix_file.classes.get_mut(tokens[1]) {
+                        Some(class) => class.rules.push(rule),
+                        None => return Err(error()),
+                    }
+                },
+                _ => (),
+            }
+        }
+        Ok(affix_file)
+    }
+
+    // The flags of a stem or of a rule, an AF alias is a number.
+    pub fn resolve_flags(&self, text: &str) -> Vec<String> {
+        if !self.flag_aliases.is_empty() {
+            if let Ok(index) = text.parse::<usize>() {
+                if index >= 1 && index <= self.flag_aliases.len() {
+                    return self.flag_aliases[index - 1].clone();
+                }
+            }
+        }
+        self.flag_mode.parse_flags(text)
+    }
+
+    fn has_flag(flags: &[String], flag: &Option<String>) -> bool {
+        match flag {
+            Some(flag) => flags.contains(flag),
+            None => false,
+        }
+    }
+
+    // Applies the classes of the flags (suffixes or prefixes) to the word,
+    // with the continuation flags of each rule up to the depth, they can be
+    // of suffixes and of prefixes. Each form says if its class allows the
+    // cross product.
+    fn expand_classes(&self, word: &str, flags: &[String], flag_suffix: bool, depth: usize,
+                      res: &mut Vec<(String, bool)>) {
+        for flag in flags {
+            let class = match self.classes.get(flag) {
+                Some(class) if class.flag_suffix == flag_suffix => class,
+                _ => continue,
+            };
+            for rule in &class.rules {
+                if let Some(form) = rule.apply(word, flag_suffix) {
+                    if depth > 0 && !rule.cont_flags.is_empty() {
+                        self.expand_classes(&form, &rule.cont_flags, true, depth - 1, res);
+                        self.expand_classes(&form, &rule.cont_flags, false, depth - 1, res);
+                    }
+                    if !AffixFile::has_flag(&rule.cont_flags, &self.need_affix) {
+                        res.push((form, class.cross_product));
+                    }
+                }
+            }
+        }
+    }
+
+    // All the forms of one stem with its flags.
+    pub fn expand(&self, stem: &str, flags: &[String]) -> Vec<String> {
+        if AffixFile::has_flag(flags, &self.forbidden_word) || AffixFile::has_flag(flags, &self.only_in_compound) {
+            return Vec::new();
+        }
+        let mut res: Vec<String> = Vec::new();
+        if !AffixFile::has_flag(flags, &self.need_affix) {
+            res.push(stem.to_string());
+        }
+
+        let mut suffixed: Vec<(String, bool)> = Vec::new();
+        self.expand_classes(stem, flags, true, 1, &mut suffixed);
+
+        let mut prefixed: Vec<(String, bool)> = Vec::new();
+        self.expand_classes(stem, flags, false, 1, &mut prefixed);
+
+        // Cross product, the prefixes that allow it over the suffixed forms
+        // that allow it.
+        let cross_prefix_flags: Vec<String> = flags.iter()
+            .filter(|flag| matches!(self.classes.get(*flag), Some(class) if !class.flag_suffix && class.cross_product))
+            .cloned()
+            .collect();
+        if !cross_prefix_flags.is_empty() {
+            for (form, flag_cross) in &suffixed {
+                if *flag_cross {
+                    let mut cross: Vec<(String, bool)> = Vec::new();
+                    self.expand_classes(form, &cross_prefix_flags, false, 0, &mut cross);
+                    res.extend(cross.into_iter().map(|(cross_form, _)| cross_form));
+                }
+            }
+        }
+
+        res.extend(suffixed.into_iter().map(|(form, _)| form));
+        res.extend(prefixed.into_iter().map(|(form, _)| form));
+        res.sort();
+        res.dedup();
+        res
+    }
+}
+
+// One line of the .dic file, "stem/flags morph", the "\/" is a '/'.
+pub fn parse_dic_line(line: &str) -> Option<(String, &str)> {
+    let entry = line.split(['\t', ' ']).next()?;
+    if entry.is_empty() {
+        return None;
+    }
+    let mut split_at = None;
+    let mut prev_ch = ' ';
+    for (i, ch) in entry.char_indices() {
+        if ch == '/' && prev_ch != '\\' && i > 0 {
+            split_at = Some(i);
+            break;
+        }
+        prev_ch = ch;
+    }
+    match split_at {
+        Some(i) => Some((entry[..i].replace("\\/", "/"), &entry[i + 1..])),
+        None => Some((entry.replace("\\/", "/"), "")),
+    }
+}
+
+pub struct NativeDictionary {
+    pub words: HashSet<String>,
+}
+
+impl NativeDictionary {
+    pub fn parse(aff_text: &str, dic_text: &str) -> Result<Self, String> {
+        let affix_file = AffixFile::parse(aff_text)?;
+        let mut words: HashSet<String> = HashSet::new();
+        // The first line is the number of stems.
+        for line in dic_text.lines().skip(1) {
+            if let Some((stem, flags_text)) = parse_dic_line(line) {
+                let flags = affix_file.resolve_flags(flags_text);
+                words.extend(affix_file.expand(&stem, &flags));
+            }
+        }
+        Ok(NativeDictionary { words })
+    }
+
+    pub fn from_files(aff_path: &str, dic_path: &str) -> Result<Self, String> {
+        let aff_text = fs::read_to_string(aff_path)
+            .map_err(|err| format!("{}: {}", aff_path, err))?;
+        let dic_text = fs::read_to_string(dic_path)
+            .map_err(|err| format!("{}: {}", dic_path, err))?;
+        NativeDictionary::parse(&aff_text, &dic_text)
+    }
+
+    // The word, or its lower case for the Title and UPPER case words, is one
+    // of the forms of the dictionary.
+    pub fn check(&self, word: &str) -> bool {
+        if self.words.contains(word) {
+            return true;
+        }
+        let lower_case_word = word.to_lowercase();
+        if self.words.contains(&lower_case_word) {
+            return true;
+        }
+        // Ex: "OPEC" for the stem "Opec", but not "opec".
+        lower_case_word != word && self.words.contains(&crate::casing::capitalize_first(&lower_case_word))
+    }
+
+    // All the forms sorted, one per line.
+    pub fn make_string(&self) -> String {
+        let mut words: Vec<&String> = self.words.iter().collect();
+        words.sort();
+        let mut res = String::with_capacity(12 * words.len());
+        for word in words {
+            res.push_str(word);
+            res.push('\n');
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forms(aff_text: &str, dic_text: &str) -> Vec<String> {
+        let native_dic = NativeDictionary::parse(aff_text, dic_text).unwrap();
+        let mut words: Vec<String> = native_dic.words.into_iter().collect();
+        words.sort();
+        words
+    }
+
+    #[test]
+    fn suffixes_and_prefixes_with_conditions() {
+        let aff_text = "SFX p Y 2\nSFX p 0 s [^os]\nSFX p ão ões ão\nPFX d N 1\nPFX d 0 des .\n";
+        assert_eq!(forms(aff_text, "3\ncasa/p\nação/p\nlápis/p\n"),
+                   vec!["ação", "ações", "casa", "casas", "lápis"]);
+        assert_eq!(forms(aff_text, "1\nfazer/d\n"), vec!["desfazer", "fazer"]);
+    }
+
+    #[test]
+    fn cross_product_only_when_both_allow_it() {
+        let aff_text = "SFX s Y 1\nSFX s r ndo r\nPFX r Y 1\nPFX r 0 re .\nPFX n N 1\nPFX n 0 nao .\n";
+        assert_eq!(forms(aff_text, "1\nler/rs\n"), vec!["lendo", "ler", "relendo", "reler"]);
+        assert_eq!(forms(aff_text, "1\nler/ns\n"), vec!["lendo", "ler", "naoler"]);
+    }
+
+    #[test]
+    fn flag_modes() {
+        let long = "FLAG long\nSFX Aa Y 1\nSFX Aa 0 s .\nSFX Bb Y 1\nSFX Bb 0 zinha .\n";
+        assert_eq!(forms(long, "1\ncasa/AaBb\n"), vec!["casa", "casas", "casazinha"]);
+        let num = "FLAG num\nSFX 101 Y 1\nSFX 101 0 s .\nSFX 7 Y 1\nSFX 7 0 zinha .\n";
+        assert_eq!(forms(num, "1\ncasa/101,7\n"), vec!["casa", "casas", "casazinha"]);
+        let utf_8 = "FLAG UTF-8\nSFX ç Y 1\nSFX ç 0 s .\n";
+        assert_eq!(forms(utf_8, "1\ncasa/ç\n"), vec!["casa", "casas"]);
+    }
+
+    #[test]
+    fn af_aliases() {
+        let aff_text = "AF 2\nAF p\nAF pd\nSFX p Y 1\nSFX p 0 s .\nPFX d Y 1\nPFX d 0 des .\nSFX c Y 1\nSFX c 0 mente/1 .\n";
+        assert_eq!(forms(aff_text, "2\ncasa/1\nfeito/2\n"),
+                   vec!["casa", "casas", "desfeito", "desfeitos", "feito", "feitos"]);
+        assert_eq!(forms(aff_text, "1\nfeliz/c\n"), vec!["feliz", "felizmente", "felizmentes"]);
+    }
+
+    #[test]
+    fn continuation_flags() {
+        // A suffix rule that continues with a suffix and with a prefix.
+        let aff_text = "SFX c Y 1\nSFX c 0 mente/si .\nSFX s Y 1\nSFX s 0 s .\nPFX i Y 1\nPFX i 0 in .\n";
+        assert_eq!(forms(aff_text, "1\nfeliz/c\n"),
+                   vec!["feliz", "felizmente", "felizmentes", "infelizmente"]);
+        // The forms of NEEDAFFIX need another affix, the forbidden words
+        // have no forms.
+        let aff_text = "NEEDAFFIX X\nFORBIDDENWORD F\nSFX a Y 1\nSFX a 0 ção/Xb .\nSFX b Y 1\nSFX b 0 es .\n";
+        assert_eq!(forms(aff_text, "2\nna/a\nerro/F\n"), vec!["na", "naçãoes"]);
+    }
+
+    #[test]
+    fn check_the_case() {
+        let native_dic = NativeDictionary::parse("", "2\ncasa\nOpec\n").unwrap();
+        assert!(native_dic.check("casa"));
+        assert!(native_dic.check("Casa"));
+        assert!(native_dic.check("CASA"));
+        assert!(native_dic.check("OPEC"));
+        assert!(!native_dic.check("opec"));
+        assert!(!native_dic.check("casas"));
+    }
+
+    #[test]
+    fn dic_lines() {
+        assert_eq!(parse_dic_line("casa/p po:nc"), Some(("casa".to_string(), "p")));
+        assert_eq!(parse_dic_line("km\\/h"), Some(("km/h".to_string(), "")));
+        assert_eq!(parse_dic_line(""), None);
+    }
+}
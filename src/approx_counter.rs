@@ -0,0 +1,273 @@
+/// Approximate counter for the exploratory runs over huge corpora, the
+/// counts use a fixed memory: a Count-Min sketch of all the n grams and the
+/// Space-Saving top k of the most frequent ones (heavy hitters). Only the
+/// top k are written. The n grams are ids of the vocabulary, that isn't
+/// fixed, it grows with the number of distinct words (not of n grams).
+///
+/// Count-Min sketch, depth rows of width counters, each n grams adds to one
+/// counter of each row and its estimate is the minimum of its counters. With
+/// the conservative update only the counters below the new estimate grow.
+/// For num_n_grams counted, with width = e / epsilon and depth = ln(1 / delta):
+///
+///     count <= estimate <= count + epsilon * num_n_grams   (probability 1 - delta)
+///
+/// Space-Saving keeps k n grams with a count and an error, a new n grams
+/// replaces the one with the smallest count, min_count, and starts with
+/// min_count + 1 and the error min_count, so:
+///
+///     count - error <= true count <= count
+///
+/// and every n grams with more than num_n_grams / k occurrences is in the
+/// top k. The count written is the smallest of the two upper bounds.
+///
+
+use std::collections::{BTreeSet, HashMap};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+
+use crate::interned_counter::Vocabulary;
+use crate::ngram_counter::{self, NGramCounter};
+
+pub const DEFAULT_NUM_TOP: usize = 100_000;
+pub const DEFAULT_EPSILON: f64 = 0.000_01;
+pub const DEFAULT_DELTA: f64 = 0.001;
+
+pub struct CountMinSketch {
+    width: usize,
+    depth: usize,
+    table: Vec<u64>,
+}
+
+impl CountMinSketch {
+    pub fn new(epsilon: f64, delta: f64) -> Self {
+        let width = (std::f64::consts::E / epsilon).ceil().max(1.0) as usize;
+        let depth = (1.0 / delta).ln().ceil().max(1.0) as usize;
+        CountMinSketch { width, depth, table: vec![0; width * depth] }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    // The counter of each row, the hashes of the rows are h1 + row * h2.
+    fn cells<K: Hash>(&self, key: &K) -> impl Iterator<Item = usize> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let hash = hasher.finish();
+        let (hash_1, hash_2) = (hash & 0xffff_ffff, (hash >> 32) | 1);
+        let width = self.width;
+        (0..self.depth).map(move |row| row * width + (hash_1.wrapping_add(row as u64 * hash_2) % width as u64) as usize)
+    }
+
+    pub fn estimate<K: Hash>(&self, key: &K) -> u64 {
+        self.cells(key).map(|cell| self.table[cell]).min().unwrap_or(0)
+    }
+
+    // Conservative update, returns the new estimate.
+    pub fn add<K: Hash>(&mut self, key: &K) -> u64 {
+        let cells: Vec<usize> = self.cells(key).collect();
+        let estimate = cells.iter().map(|cell| self.table[*cell]).min().unwrap_or(0) + 1;
+        for cell in cells {
+            self.table[cell] = self.table[cell].max(estimate);
+        }
+        estimate
+    }
+}
+
+pub struct SpaceSaving<const N: usize> {
+    capacity: usize,
+    // N grams -> (count, error).
+    entries: HashMap<[u32; N], (u64, u64)>,
+    by_count: BTreeSet<(u64, [u32; N])>,
+}
+
+impl<const N: usize> SpaceSaving<N> {
+    pub fn new(capacity: usize) -> Self {
+        SpaceSaving {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            by_count: BTreeSet::new(),
+        }
+    }
+
+    pub fn add(&mut self, ids: [u32; N]) {
+        let (count, error) = match self.entries.get(&ids) {
+            Some((count, error)) => {
+                self.by_count.remove(&(*count, ids));
+                (*count + 1, *error)
+            },
+            None if self.entries.len() < self.capacity => (1, 0),
+            None => {
+                let (min_count, min_ids) = self.by_count.pop_first().unwrap();
+                self.entries.remove(&min_ids);
+                (min_count + 1, min_count)
+            },
+        };
+        self.entries.insert(ids, (count, error));
+        self.by_count.insert((count, ids));
+    }
+
+    pub fn min_count(&self) -> u64 {
+        if self.entries.len() < self.capacity {
+            return 0;
+        }
+        self.by_count.first().map(|(count, _)| *count).unwrap_or(0)
+    }
+}
+
+pub struct ApproxCounter<const N: usize> {
+    sketch: CountMinSketch,
+    top: SpaceSaving<N>,
+    num_n_grams: u64,
+    epsilon: f64,
+    delta: f64,
+}
+
+impl<const N: usize> ApproxCounter<N> {
+    pub fn new(num_top: usize, epsilon: f64, delta: f64) -> Self {
+        ApproxCounter {
+            sketch: CountMinSketch::new(epsilon, delta),
+            top: SpaceSaving::new(num_top),
+            num_n_grams: 0,
+            epsilon,
+            delta,
+        }
+    }
+
+    // The top k with the count and the smallest count possible, sorted by
+    // key.
+    fn top_counts(&self, vocabulary: &Vocabulary) -> Vec<(String, u64, u64)> {
+        let mut res: Vec<(String, u64, u64)> = self.top.entries.iter()
+            .map(|(ids, (count, error))| {
+                let upper = (*count).min(self.sketch.estimate(ids));
+                (vocabulary.make_key(ids), upper, (count - error).min(upper))
+            })
+            .collect();
+        res.sort();
+        res
+    }
+}
+
+impl<const N: usize> NGramCounter<N> for ApproxCounter<N> {
+    fn count(&mut self, _vocabulary: &Vocabulary, ids: [u32; N]) -> io::Result<()> {
+        self.sketch.add(&ids);
+        self.top.add(ids);
+        self.num_n_grams += 1;
+        Ok(())
+    }
+
+    fn write_sorted(&mut self, vocabulary: &Vocabulary, out: &mut dyn Write) -> io::Result<u64> {
+        let top_counts = self.top_counts(vocabulary);
+        for (key, count, _) in &top_counts {
+            ngram_counter::write_count_line(out, key, *count)?;
+        }
+        Ok(top_counts.len() as u64)
+    }
+
+    fn error_summary(&self) -> Option<String> {
+        Some(format!("Approximate counts of {} n grams, sketch {} x {}: count <= estimate <= count + {:.0} \
+                      (probability {}), top {} complete above {} occurrences.",
+            self.num_n_grams, self.sketch.depth(), self.sketch.width(),
+            (self.epsilon * self.num_n_grams as f64).ceil(), 1.0 - self.delta,
+            self.top.capacity, self.top.min_count()))
+    }
+
+    fn make_bounds_string(&self, vocabulary: &Vocabulary) -> Option<String> {
+        let mut res = String::from("n_grams\tcount\tmin_count\n");
+        for (key, count, min_count) in self.top_counts(vocabulary) {
+            res.push_str(&format!("{}\t{}\t{}\n", key, count, min_count));
+        }
+        Some(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A Zipf stream, the id i has 1000 / (i + 1) occurrences, in a
+    // pseudo random order. Returns the stream and the true counts.
+    fn skewed_stream(num_ids: u32) -> (Vec<[u32; 1]>, Vec<u64>) {
+        let true_counts: Vec<u64> = (0..num_ids).map(|id| 1000 / (id as u64 + 1)).collect();
+        let mut stream: Vec<[u32; 1]> = Vec::new();
+        for (id, count) in true_counts.iter().enumerate() {
+            stream.extend(std::iter::repeat_n([id as u32], *count as usize));
+        }
+        let mut state: u64 = 12345;
+        for i in (1..stream.len()).rev() {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            stream.swap(i, (state >> 33) as usize % (i + 1));
+        }
+        (stream, true_counts)
+    }
+
+    #[test]
+    fn count_min_sketch_bounds() {
+        let (stream, true_counts) = skewed_stream(2000);
+        let (epsilon, delta) = (0.01, 0.01);
+        let mut sketch = CountMinSketch::new(epsilon, delta);
+        for ids in &stream {
+            sketch.add(ids);
+        }
+        let max_error = (epsilon * stream.len() as f64).ceil() as u64;
+        let mut num_over = 0;
+        for (id, count) in true_counts.iter().enumerate() {
+            let estimate = sketch.estimate(&[id as u32]);
+            assert!(estimate >= *count);
+            if estimate > count + max_error {
+                num_over += 1;
+            }
+        }
+        // Each estimate is over the bound with probability at most delta.
+        assert!(num_over as f64 <= delta * true_counts.len() as f64);
+    }
+
+    #[test]
+    fn space_saving_bounds() {
+        let (stream, true_counts) = skewed_stream(2000);
+        let num_top = 100;
+        let mut top: SpaceSaving<1> = SpaceSaving::new(num_top);
+        for ids in &stream {
+            top.add(*ids);
+        }
+        assert_eq!(top.entries.len(), num_top);
+        for (ids, (count, error)) in &top.entries {
+            let true_count = true_counts[ids[0] as usize];
+            assert!(count - error <= true_count && true_count <= *count);
+        }
+        // The heavy hitters are all in the top k.
+        let min_heavy_count = stream.len() as u64 / num_top as u64;
+        for (id, count) in true_counts.iter().enumerate() {
+            if *count > min_heavy_count {
+                assert!(top.entries.contains_key(&[id as u32]));
+            }
+        }
+        assert!(top.min_count() <= min_heavy_count + 1);
+    }
+
+    #[test]
+    fn written_counts_are_upper_bounds() {
+        let (stream, true_counts) = skewed_stream(500);
+        let mut vocabulary = Vocabulary::new();
+        for id in 0..true_counts.len() {
+            vocabulary.intern(&format!("w{}", id));
+        }
+        let mut counter: ApproxCounter<1> = ApproxCounter::new(50, 0.001, 0.01);
+        for ids in &stream {
+            counter.count(&vocabulary, *ids).unwrap();
+        }
+        let bounds = counter.make_bounds_string(&vocabulary).unwrap();
+        assert_eq!(bounds.lines().count(), 51);
+        for line in bounds.lines().skip(1) {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let true_count = true_counts[fields[0][1..].parse::<usize>().unwrap()];
+            let (count, min_count) = (fields[1].parse::<u64>().unwrap(), fields[2].parse::<u64>().unwrap());
+            assert!(min_count <= true_count && true_count <= count);
+        }
+    }
+}
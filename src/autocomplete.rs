@@ -0,0 +1,357 @@
+/// Prefix autocomplete index over the words frequency file, a trie written
+/// to a binary file that is memory mapped at startup (see mmap_file.rs).
+///
+/// The trie has two roots, one with the words as they are and one with the
+/// words accent folded ("não" -> "nao"), so "nao" finds "não". Each node
+/// keeps the ids of the most frequent words below it, the words have the
+/// ids by descending count, so a lookup is a walk over the chars of the
+/// prefix and the top words are read from the last node.
+///
+/// Binary format, little endian:
+///
+///     magic        "PTACIDX1"
+///     header       num_words, num_nodes, num_edges, num_tops, max_top,
+///                  exact_root, folded_root, blob_len         (u32 each)
+///     words        blob_offset u32, len u32, count u64       (num_words)
+///     nodes        first_edge u32, num_edges u32,
+///                  first_top u32, num_tops u32               (num_nodes)
+///     edges        char u32, child u32, sorted by char       (num_edges)
+///     tops         word id u32                               (num_tops)
+///     blob         the UTF-8 words
+///
+/// A lookup gives up to max_top words. The tables are checked when the
+/// index is opened.
+///
+
+use std::fs;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::casing;
+use crate::freq_dic;
+use crate::mmap_file::{self, MappedFile};
+
+const MAGIC: &[u8; 8] = b"PTACIDX1";
+const HEADER_LEN: usize = 8 + 8 * 4;
+const WORD_LEN: usize = 16;
+const NODE_LEN: usize = 16;
+const EDGE_LEN: usize = 8;
+
+pub const DEFAULT_MAX_TOP: usize = 10;
+
+#[derive(Default)]
+struct BuildNode {
+    children: BTreeMap<char, usize>,
+    top: Vec<u32>,
+}
+
+// The trie in memory, before it's written.
+struct TrieBuilder {
+    nodes: Vec<BuildNode>,
+    max_top: usize,
+}
+
+impl TrieBuilder {
+    fn new_root(&mut self) -> usize {
+        self.nodes.push(BuildNode::default());
+        self.nodes.len() - 1
+    }
+
+    // The words are inserted by descending count, the first ones to reach a
+    // node are its top words.
+    fn insert(&mut self, root: usize, key: &str, word_id: u32) {
+        let mut node = root;
+        self.push_top(node, word_id);
+        for ch in key.chars() {
+            node = match self.nodes[node].children.get(&ch) {
+                Some(child) => *child,
+                None => {
+                    self.nodes.push(BuildNode::default());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[node].children.insert(ch, child);
+                    child
+                },
+            };
+            self.push_top(node, word_id);
+        }
+    }
+
+    fn push_top(&mut self, node: usize, word_id: u32) {
+        let top = &mut self.nodes[node].top;
+        if top.len() < self.max_top && !top.contains(&word_id) {
+            top.push(word_id);
+        }
+    }
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: usize) {
+    bytes.extend_from_slice(&(value as u32).to_le_bytes());
+}
+
+// Makes the binary index of the words and their counts.
+pub fn build_index(word_freq: &HashMap<String, u64>, max_top: usize) -> Vec<u8> {
+    let mut words: Vec<(&String, u64)> = word_freq.iter().map(|(word, count)| (word, *count)).collect();
+    words.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let mut trie = TrieBuilder { nodes: Vec::new(), max_top };
+    let exact_root = trie.new_root();
+    let folded_root = trie.new_root();
+    for (word_id, (word, _)) in words.iter().enumerate() {
+        trie.insert(exact_root, word, word_id as u32);
+        trie.insert(folded_root, &casing::accent_fold(word), word_id as u32);
+    }
+
+    let num_edges: usize = trie.nodes.iter().map(|node| node.children.len()).sum();
+    let num_tops: usize = trie.nodes.iter().map(|node| node.top.len()).sum();
+    let blob_len: usize = words.iter().map(|(word, _)| word.len()).sum();
+
+    let mut bytes: Vec<u8> = Vec::with_capacity(HEADER_LEN + words.len() * WORD_LEN + trie.nodes.len() * NODE_LEN
+        + num_edges * EDGE_LEN + num_tops * 4 + blob_len);
+    bytes.extend_from_slice(MAGIC);
+    for value in [words.len(), trie.nodes.len(), num_edges, num_tops, max_top, exact_root, folded_root, blob_len] {
+        push_u32(&mut bytes, value);
+    }
+
+    let mut blob_offset = 0;
+    for (word, count) in &words {
+        push_u32(&mut bytes, blob_offset);
+        push_u32(&mut bytes, word.len());
+        bytes.extend_from_slice(&count.to_le_bytes());
+        blob_offset += word.len();
+    }
+
+    let mut first_edge = 0;
+    let mut first_top = 0;
+    for node in &trie.nodes {
+        for value in [first_edge, node.children.len(), first_top, node.top.len()] {
+            push_u32(&mut bytes, value);
+        }
+        first_edge += node.children.len();
+        first_top += node.top.len();
+    }
+    for node in &trie.nodes {
+        for (ch, child) in &node.children {
+            push_u32(&mut bytes, *ch as usize);
+            push_u32(&mut bytes, *child);
+        }
+    }
+    for node in &trie.nodes {
+        for word_id in &node.top {
+            push_u32(&mut bytes, *word_id as usize);
+        }
+    }
+    for (word, _) in &words {
+        bytes.extend_from_slice(word.as_bytes());
+    }
+    bytes
+}
+
+pub fn build_index_file(word_freq_path: &str, out_index_path: &str, max_top: usize) -> Result<usize, String> {
+    let word_freq = freq_dic::load_freq_file(word_freq_path)?;
+    let bytes = build_index(&word_freq, max_top);
+    fs::write(out_index_path, &bytes)
+        .map_err(|err| format!("{}: {}", out_index_path, err))?;
+    Ok(bytes.len())
+}
+
+pub struct AutocompleteIndex {
+    data: MappedFile,
+    max_top: usize,
+    exact_root: usize,
+    folded_root: usize,
+    words_offset: usize,
+    nodes_offset: usize,
+    edges_offset: usize,
+    tops_offset: usize,
+    blob_offset: usize,
+}
+
+impl AutocompleteIndex {
+    /// # Safety
+    ///
+    /// The one of MappedFile::open(), the file must not be modified nor
+    /// truncated while the index is open.
+    pub unsafe fn open(path: &str) -> Result<Self, String> {
+        // SAFETY: the contract of open().
+        let data = unsafe { MappedFile::open(path)? };
+        AutocompleteIndex::from_data(data)
+            .map_err(|err| format!("{}: {}", path, err))
+    }
+
+    // Checks all the tables, so the lookups can't read out of the file nor
+    // loop, a corrupted index is an Err and not a panic later.
+    pub fn from_data(data: MappedFile) -> Result<Self, String> {
+        if data.len() < HEADER_LEN || &data[..8] != MAGIC {
+            return Err("not an autocomplete index".to_string());
+        }
+        let header: Vec<usize> = (0..8).map(|i| mmap_file::read_u32(&data, 8 + 4 * i) as usize).collect();
+        let [num_words, num_nodes, num_edges, num_tops, max_top, exact_root, folded_root, blob_len] = header[..] else {
+            return Err("bad header".to_string());
+        };
+
+        let words_offset = HEADER_LEN;
+        let nodes_offset = words_offset + num_words * WORD_LEN;
+        let edges_offset = nodes_offset + num_nodes * NODE_LEN;
+        let tops_offset = edges_offset + num_edges * EDGE_LEN;
+        let blob_offset = tops_offset + num_tops * 4;
+        if data.len() != blob_offset + blob_len || exact_root >= num_nodes || folded_root >= num_nodes {
+            return Err("wrong size".to_string());
+        }
+
+        for word_id in 0..num_words {
+            let offset = words_offset + word_id * WORD_LEN;
+            let start = mmap_file::read_u32(&data, offset) as usize;
+            let len = mmap_file::read_u32(&data, offset + 4) as usize;
+            if start + len > blob_len || std::str::from_utf8(&data[blob_offset + start..blob_offset + start + len]).is_err() {
+                return Err(format!("bad word {}", word_id));
+            }
+        }
+        for node in 0..num_nodes {
+            let field = |i: usize| mmap_file::read_u32(&data, nodes_offset + node * NODE_LEN + 4 * i) as usize;
+            let (first_edge, node_edges, first_top, node_tops) = (field(0), field(1), field(2), field(3));
+            if first_edge + node_edges > num_edges || first_top + node_tops > num_tops || node_tops > max_top {
+                return Err(format!("bad node {}", node));
+            }
+            // The binary search of child() needs the chars sorted.
+            let mut prev_ch: Option<u32> = None;
+            for edge in first_edge..first_edge + node_edges {
+                let ch = mmap_file::read_u32(&data, edges_offset + edge * EDGE_LEN);
+                let child = mmap_file::read_u32(&data, edges_offset + edge * EDGE_LEN + 4) as usize;
+                if char::from_u32(ch).is_none() || prev_ch.is_some_and(|prev_ch| prev_ch >= ch) || child >= num_nodes {
+                    return Err(format!("bad edge {}", edge));
+                }
+                prev_ch = Some(ch);
+            }
+        }
+        for top in 0..num_tops {
+            if mmap_file::read_u32(&data, tops_offset + 4 * top) as usize >= num_words {
+                return Err(format!("bad top word {}", top));
+            }
+        }
+
+        Ok(AutocompleteIndex {
+            data,
+            max_top,
+            exact_root,
+            folded_root,
+            words_offset,
+            nodes_offset,
+            edges_offset,
+            tops_offset,
+            blob_offset,
+        })
+    }
+
+    pub fn max_top(&self) -> usize {
+        self.max_top
+    }
+
+    pub fn is_mapped(&self) -> bool {
+        self.data.is_mapped()
+    }
+
+    fn word(&self, word_id: usize) -> (&str, u64) {
+        let offset = self.words_offset + word_id * WORD_LEN;
+        let start = self.blob_offset + mmap_file::read_u32(&self.data, offset) as usize;
+        let len = mmap_file::read_u32(&self.data, offset + 4) as usize;
+        let count = mmap_file::read_u64(&self.data, offset + 8);
+        (std::str::from_utf8(&self.data[start..start + len]).unwrap_or(""), count)
+    }
+
+    fn node_field(&self, node: usize, field: usize) -> usize {
+        mmap_file::read_u32(&self.data, self.nodes_offset + node * NODE_LEN + 4 * field) as usize
+    }
+
+    // Binary search of the char in the edges of the node.
+    fn child(&self, node: usize, ch: char) -> Option<usize> {
+        let first_edge = self.node_field(node, 0);
+        let (mut low, mut high) = (first_edge, first_edge + self.node_field(node, 1));
+        while low < high {
+            let mid = (low + high) / 2;
+            let offset = self.edges_offset + mid * EDGE_LEN;
+            let edge_ch = mmap_file::read_u32(&self.data, offset);
+            match edge_ch.cmp(&(ch as u32)) {
+                std::cmp::Ordering::Equal => return Some(mmap_file::read_u32(&self.data, offset + 4) as usize),
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+            }
+        }
+        None
+    }
+
+    // The k most frequent words that start with the prefix, accent and case
+    // insensitive with flag_fold.
+    pub fn top_k(&self, prefix: &str, k: usize, flag_fold: bool) -> Vec<(&str, u64)> {
+        let (mut node, key) = if flag_fold {
+            (self.folded_root, casing::accent_fold(prefix))
+        } else {
+            (self.exact_root, prefix.to_string())
+        };
+        for ch in key.chars() {
+            node = match self.child(node, ch) {
+                Some(child) => child,
+                None => return Vec::new(),
+            };
+        }
+        let first_top = self.node_field(node, 2);
+        let num_top = self.node_field(node, 3).min(k);
+        (first_top..first_top + num_top)
+            .map(|i| self.word(mmap_file::read_u32(&self.data, self.tops_offset + 4 * i) as usize))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_index_bytes() -> Vec<u8> {
+        let word_freq: HashMap<String, u64> = [("não", 50), ("nada", 30), ("nação", 20), ("casa", 10)].iter()
+            .map(|(word, count)| (word.to_string(), *count))
+            .collect();
+        build_index(&word_freq, 2)
+    }
+
+    fn header(bytes: &[u8], i: usize) -> usize {
+        mmap_file::read_u32(bytes, 8 + 4 * i) as usize
+    }
+
+    fn put_u32(bytes: &mut [u8], offset: usize, value: u32) {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    #[test]
+    fn top_k_exact_and_folded() {
+        let index = AutocompleteIndex::from_data(MappedFile::from_bytes(make_index_bytes())).unwrap();
+        assert_eq!(index.max_top(), 2);
+        assert_eq!(index.top_k("na", 5, false), vec![("nada", 30), ("nação", 20)]);
+        assert_eq!(index.top_k("nao", 5, true), vec![("não", 50)]);
+        assert!(index.top_k("x", 5, true).is_empty());
+    }
+
+    #[test]
+    fn corrupted_index_is_an_error() {
+        let bytes = make_index_bytes();
+        let (num_words, num_nodes, num_edges) = (header(&bytes, 0), header(&bytes, 1), header(&bytes, 2));
+        let nodes_offset = HEADER_LEN + num_words * WORD_LEN;
+        let edges_offset = nodes_offset + num_nodes * NODE_LEN;
+        let tops_offset = edges_offset + num_edges * EDGE_LEN;
+        let corrupt = |offset: usize, value: u32| {
+            let mut bytes = bytes.clone();
+            put_u32(&mut bytes, offset, value);
+            AutocompleteIndex::from_data(MappedFile::from_bytes(bytes))
+        };
+
+        assert!(AutocompleteIndex::from_data(MappedFile::from_bytes(bytes[..bytes.len() - 1].to_vec())).is_err());
+        // The blob offset and the length of the first word.
+        assert!(corrupt(HEADER_LEN, 1000).is_err());
+        assert!(corrupt(HEADER_LEN + 4, 1000).is_err());
+        // The first edge and the first top of the root.
+        assert!(corrupt(nodes_offset, num_edges as u32).is_err());
+        assert!(corrupt(nodes_offset + 8, 1000).is_err());
+        // The char and the child of the first edge.
+        assert!(corrupt(edges_offset, 0xd800).is_err());
+        assert!(corrupt(edges_offset + 4, num_nodes as u32).is_err());
+        // The first top word.
+        assert!(corrupt(tops_offset, num_words as u32).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+/// Capitalization of the words. The corrections are computed on a case
+/// folded key and then the case pattern of the original word is applied
+/// again to the corrected word:
+///
+///     Lower   acta  -> ata      the corrected word is used as it is, it
+///                               can be a name, "alemanha" -> "Alemanha"
+///     Title   Acta  -> Ata
+///     Upper   ACTA  -> ATA
+///     Mixed   McDonald          the corrected word is used as it is
+///
+/// The first word of a sentence is marked, because there the Title case
+/// comes from the position and not from the word.
+///
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CasePattern {
+    Lower,
+    Title,
+    Upper,
+    Mixed,
+}
+
+impl CasePattern {
+    pub fn of(word: &str) -> Self {
+        let mut chars = word.chars().filter(|ch| ch.is_alphabetic());
+        let first_upper = match chars.next() {
+            Some(ch) => ch.is_uppercase(),
+            None => return CasePattern::Lower,
+        };
+        let mut num_rest = 0;
+        let mut num_rest_upper = 0;
+        for ch in chars {
+            num_rest += 1;
+            if ch.is_uppercase() {
+                num_rest_upper += 1;
+            }
+        }
+        match (first_upper, num_rest_upper) {
+            (false, 0) => CasePattern::Lower,
+            // A single upper case letter, ex: "A", is Title.
+            (true, 0) => CasePattern::Title,
+            (true, n) if n == num_rest => CasePattern::Upper,
+            _ => CasePattern::Mixed,
+        }
+    }
+
+    pub fn apply(&self, word: &str) -> String {
+        match self {
+            CasePattern::Lower | CasePattern::Mixed => word.to_string(),
+            CasePattern::Title => capitalize_first(word),
+            CasePattern::Upper => word.to_uppercase(),
+        }
+    }
+}
+
+// The key used for the corrections and the counts.
+pub fn case_fold(word: &str) -> String {
+    word.to_lowercase()
+}
+
+// The case folded word without the diacritics, "Não" -> "nao".
+pub fn accent_fold(word: &str) -> String {
+    word.nfd()
+        .filter(|ch| !is_combining_mark(*ch))
+        .collect::<String>()
+        .to_lowercase()
+}
+
+pub fn capitalize_first(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+// Gives to the new word the case pattern of the original word.
+pub fn apply_case_of(original: &str, new_word: &str) -> String {
+    CasePattern::of(original).apply(new_word)
+}
+
+// A word of the text with its case folded key, the case pattern and if it's
+// the first word of a sentence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CasedWord {
+    pub key: String,
+    pub pattern: CasePattern,
+    pub flag_sentence_start: bool,
+}
+
+impl CasedWord {
+    pub fn new(original: &str, flag_sentence_start: bool) -> Self {
+        CasedWord {
+            key: case_fold(original),
+            pattern: CasePattern::of(original),
+            flag_sentence_start,
+        }
+    }
+
+    // The corrected word with the case of the original word.
+    pub fn restore(&self, corrected: &str) -> String {
+        self.pattern.apply(corrected)
+    }
+}
+
+// The text between two words ends a sentence, not after ':' as in
+// Portuguese the next word isn't capitalized.
+pub fn ends_sentence(gap: &str) -> bool {
+    gap.contains(['.', '!', '?'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sentence_ends() {
+        assert!(["! ", "? ", ". ", ".\n"].iter().all(|gap| ends_sentence(gap)));
+        assert!(!ends_sentence(": "));
+        assert!(!ends_sentence(", "));
+    }
+}
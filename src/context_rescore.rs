@@ -0,0 +1,96 @@
+/// Context-aware choice between the alternatives of an ambiguous word,
+/// using the 2 grams counts of a first pass over the corpus.
+///
+/// A word is ambiguous when the rules found more then one candidate, or
+/// when the word is valid but some of its accent variants are also valid
+/// words, ex: "à" vs "a" or "pára" vs "para".
+///
+/// The score of an alternative with the left and the right neighbors is:
+///
+///     score = ln(1 + c(left alt)) + ln(1 + c(alt right)) + 0.1 * ln(1 + c(alt))
+///
+/// The unigram term only breaks ties between alternatives that were never
+/// seen next to the neighbors.
+///
+
+use std::collections::{BTreeMap, HashMap};
+
+const UNIGRAM_WEIGHT: f64 = 0.1;
+
+pub struct ContextRescorer<'a> {
+    dic_2_grams_freq_words: &'a BTreeMap<String, u64>,
+    word_freq: &'a HashMap<String, u64>,
+}
+
+impl<'a> ContextRescorer<'a> {
+    pub fn new(dic_2_grams_freq_words: &'a BTreeMap<String, u64>, word_freq: &'a HashMap<String, u64>) -> Self {
+        ContextRescorer { dic_2_grams_freq_words, word_freq }
+    }
+
+    fn count_2_grams(&self, word_1: &str, word_2: &str) -> u64 {
+        let key = word_1.to_string() + " " + word_2;
+        self.dic_2_grams_freq_words.get(&key).copied().unwrap_or(0)
+    }
+
+    pub fn score(&self, left: Option<&str>, word: &str, right: Option<&str>) -> f64 {
+        let mut score = 0.0;
+        if let Some(left_word) = left {
+            score += (1.0 + self.count_2_grams(left_word, word) as f64).ln();
+        }
+        if let Some(right_word) = right {
+            score += (1.0 + self.count_2_grams(word, right_word) as f64).ln();
+        }
+        let freq = self.word_freq.get(word).copied().unwrap_or(0);
+        score + UNIGRAM_WEIGHT * (1.0 + freq as f64).ln()
+    }
+
+    // Chooses the alternative with the best score, the first alternative
+    // (the choice of the first pass) wins the ties.
+    pub fn choose<'b>(&self, left: Option<&str>, alternatives: &'b [String], right: Option<&str>) -> &'b str {
+        let mut best = &alternatives[0];
+        let mut best_score = self.score(left, best, right);
+        for alt in &alternatives[1..] {
+            let score = self.score(left, alt, right);
+            if score > best_score {
+                best = alt;
+                best_score = score;
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alternatives(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn context_flips_the_first_pass_choice() {
+        let dic_2_grams: BTreeMap<String, u64> = BTreeMap::from([
+            ("vou à".to_string(), 12),
+            ("à praia".to_string(), 7),
+            ("a casa".to_string(), 30),
+        ]);
+        let word_freq: HashMap<String, u64> = HashMap::from([("a".to_string(), 1000), ("à".to_string(), 100)]);
+        let context_rescorer = ContextRescorer::new(&dic_2_grams, &word_freq);
+        let alternatives = alternatives(&["a", "à"]);
+
+        // "vou a praia" -> "vou à praia", the 2 grams win over the unigrams.
+        assert_eq!(context_rescorer.choose(Some("vou"), &alternatives, Some("praia")), "à");
+        // "a casa" keeps the first pass choice.
+        assert_eq!(context_rescorer.choose(None, &alternatives, Some("casa")), "a");
+    }
+
+    #[test]
+    fn first_alternative_wins_the_ties() {
+        let dic_2_grams: BTreeMap<String, u64> = BTreeMap::new();
+        let word_freq: HashMap<String, u64> = HashMap::new();
+        let context_rescorer = ContextRescorer::new(&dic_2_grams, &word_freq);
+        assert_eq!(context_rescorer.choose(Some("vou"), &alternatives(&["para", "pára"]), None), "para");
+        assert_eq!(context_rescorer.choose(Some("vou"), &alternatives(&["pára", "para"]), None), "pára");
+    }
+}
@@ -0,0 +1,103 @@
+/// Audit trail of the corrections, every original word that wasn't in the
+/// HunSpell dictionary with the word it was mapped to, the rule that fired,
+/// the HunSpell suggestions considered and the number of occurrences in the
+/// corpus. It's written as a tab separated file, to be reviewed before the
+/// release of the frequency files:
+///
+///     original  corrected  rule  suggestions  count
+///     acta      ata        silent_consonant  ata acto acta  127
+///
+/// The words that no rule could correct have an empty corrected column
+/// and the rule "not_found". The manual overrides have the rules
+/// "override_map", "override_accept" and "override_reject".
+///
+
+use std::fs;
+use std::collections::BTreeMap;
+
+use crate::ortho_rules::RuleMatch;
+use crate::word_corrector::Correction;
+use crate::overrides;
+
+pub const RULE_NOT_FOUND: &str = "not_found";
+
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub corrected: String,
+    pub rule_name: String,
+    pub suggestions: Vec<String>,
+    pub count: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct CorrectionAudit {
+    // Key is the original word.
+    pub entries: BTreeMap<String, AuditEntry>,
+}
+
+impl CorrectionAudit {
+    pub fn new() -> Self {
+        CorrectionAudit { entries: BTreeMap::new() }
+    }
+
+    // Records one occurrence of the word, the valid words aren't recorded.
+    pub fn record(&mut self, original: &str, correction: &Correction) {
+        if let Some(entry) = self.entries.get_mut(original) {
+            entry.count += 1;
+            return;
+        }
+        let (corrected, rule_name, suggestions) = match correction {
+            Correction::Valid => return,
+            Correction::Overridden(rule_match) => {
+                (rule_match.replacement.clone(), rule_match.rule_name.clone(), Vec::new())
+            },
+            Correction::Rejected => {
+                (String::new(), overrides::RULE_OVERRIDE_REJECT.to_string(), Vec::new())
+            },
+            Correction::Corrected(ranked, suggestion_vec) => {
+                (ranked.best.replacement.clone(), ranked.best.rule_name.clone(), suggestion_vec.clone())
+            },
+            Correction::NotFound(suggestion_vec) => {
+                (String::new(), RULE_NOT_FOUND.to_string(), suggestion_vec.clone())
+            },
+        };
+        self.entries.insert(original.to_string(), AuditEntry { corrected, rule_name, suggestions, count: 1 });
+    }
+
+    // Records one occurrence of a rule applied outside of correct(), ex: the
+    // lower case rules over the original word.
+    pub fn record_rule_match(&mut self, original: &str, rule_match: &RuleMatch) {
+        self.record(original, &Correction::Overridden(rule_match.clone()));
+    }
+
+    // Occurrences affected by each rule.
+    pub fn count_by_rule(&self) -> BTreeMap<String, u64> {
+        let mut res: BTreeMap<String, u64> = BTreeMap::new();
+        for entry in self.entries.values() {
+            *res.entry(entry.rule_name.clone()).or_insert(0) += entry.count;
+        }
+        res
+    }
+
+    pub fn make_string(&self) -> String {
+        let mut res = String::with_capacity(100 * (self.entries.len() + 1));
+        res.push_str("original\tcorrected\trule\tsuggestions\tcount\n");
+        for (original, entry) in &self.entries {
+            res.push_str(original);
+            res.push('\t');
+            res.push_str(&entry.corrected);
+            res.push('\t');
+            res.push_str(&entry.rule_name);
+            res.push('\t');
+            res.push_str(&entry.suggestions.join(" "));
+            res.push('\t');
+            res.push_str(&entry.count.to_string());
+            res.push('\n');
+        }
+        res
+    }
+
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        fs::write(path, self.make_string())
+    }
+}
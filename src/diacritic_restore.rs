@@ -0,0 +1,215 @@
+/// Restoration of the diacritics of Portuguese text written without them,
+/// ex: "nao e possivel" -> "não é possível".
+///
+/// The candidates of each word are the word itself and its accent variants
+/// (see accent_confusion.rs) that exist in the words frequency file. The
+/// most probable sequence of candidates is found with a Viterbi decode over
+/// a bigram model made from the 2 grams file, interpolated with the
+/// unigram model:
+///
+///     P(w_2 | w_1) = L * c(w_1 w_2) / c(w_1) + (1 - L) * P(w_2)
+///     P(w)         = (c(w) + 1) / (N + V)
+///
+/// The counts are case folded, the case of each input word is kept in the
+/// output. The text between the words is copied as it is, and the context
+/// restarts after the end of a sentence.
+///
+
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::accent_confusion::AccentConfusion;
+use crate::freq_dic;
+use crate::casing::{self, apply_case_of};
+
+// Weight of the bigram model in the interpolation.
+const BIGRAM_LAMBDA: f64 = 0.8;
+
+pub struct DiacriticRestorer {
+    // Case folded counts.
+    word_freq: HashMap<String, u64>,
+    dic_2_grams: HashMap<String, u64>,
+    accent_confusion: AccentConfusion,
+    reg_ex_pt: Regex,
+    num_words: u64,
+}
+
+impl DiacriticRestorer {
+    pub fn new(word_freq: &HashMap<String, u64>, dic_2_grams: &HashMap<String, u64>, accent_confusion: AccentConfusion) -> Self {
+        let mut folded_word_freq: HashMap<String, u64> = HashMap::new();
+        for (word, count) in word_freq {
+            *folded_word_freq.entry(casing::case_fold(word)).or_insert(0) += count;
+        }
+        let mut folded_2_grams: HashMap<String, u64> = HashMap::new();
+        for (words, count) in dic_2_grams {
+            *folded_2_grams.entry(casing::case_fold(words)).or_insert(0) += count;
+        }
+        let num_words = folded_word_freq.values().sum();
+
+        DiacriticRestorer {
+            word_freq: folded_word_freq,
+            dic_2_grams: folded_2_grams,
+            accent_confusion,
+            reg_ex_pt: crate::LangRegEx::new(crate::Lang::PT).reg_ex_comp_word_pattern,
+            num_words,
+        }
+    }
+
+    pub fn from_files(word_freq_path: &str, dic_2_grams_path: &str, accent_confusion: AccentConfusion) -> Result<Self, String> {
+        let word_freq = freq_dic::load_freq_file(word_freq_path)?;
+        let dic_2_grams = freq_dic::load_freq_file(dic_2_grams_path)?;
+        Ok(DiacriticRestorer::new(&word_freq, &dic_2_grams, accent_confusion))
+    }
+
+    fn log_prob_unigram(&self, word: &str) -> f64 {
+        let count = self.word_freq.get(word).copied().unwrap_or(0);
+        let vocab_size = self.word_freq.len() as f64 + 1.0;
+        ((count as f64 + 1.0) / (self.num_words as f64 + vocab_size)).ln()
+    }
+
+    fn log_prob_bigram(&self, prev_word: &str, word: &str) -> f64 {
+        let prob_unigram = self.log_prob_unigram(word).exp();
+        let count_prev = self.word_freq.get(prev_word).copied().unwrap_or(0);
+        let mut prob_bigram = 0.0;
+        if count_prev > 0 {
+            let key = prev_word.to_string() + " " + word;
+            let count = self.dic_2_grams.get(&key).copied().unwrap_or(0);
+            prob_bigram = count as f64 / count_prev as f64;
+        }
+        (BIGRAM_LAMBDA * prob_bigram + (1.0 - BIGRAM_LAMBDA) * prob_unigram).ln()
+    }
+
+    // The candidates of a lower case word, the word itself is always the
+    // first one, even if it isn't known.
+    pub fn candidates(&self, lower_case_word: &str) -> Vec<String> {
+        let mut res = vec![lower_case_word.to_string()];
+        for (variant, _cost) in self.accent_confusion.variants_iter(lower_case_word) {
+            if self.word_freq.contains_key(&variant) && !res.contains(&variant) {
+                res.push(variant);
+            }
+        }
+        res
+    }
+
+    // Viterbi decode of a sequence of lower case words.
+    pub fn restore_words(&self, words: &[String]) -> Vec<String> {
+        if words.is_empty() {
+            return Vec::new();
+        }
+        let candidates: Vec<Vec<String>> = words.iter().map(|word| self.candidates(word)).collect();
+
+        let mut scores: Vec<f64> = candidates[0].iter().map(|cand| self.log_prob_unigram(cand)).collect();
+        let mut back_pointers: Vec<Vec<usize>> = vec![Vec::new()];
+        for i in 1..candidates.len() {
+            let mut new_scores = Vec::with_capacity(candidates[i].len());
+            let mut pointers = Vec::with_capacity(candidates[i].len());
+            for cand in &candidates[i] {
+                let mut best = (f64::NEG_INFINITY, 0);
+                for (k, prev_cand) in candidates[i - 1].iter().enumerate() {
+                    let score = scores[k] + self.log_prob_bigram(prev_cand, cand);
+                    if score > best.0 {
+                        best = (score, k);
+                    }
+                }
+                new_scores.push(best.0);
+                pointers.push(best.1);
+            }
+            scores = new_scores;
+            back_pointers.push(pointers);
+        }
+
+        // Follows the back pointers from the best last candidate.
+        let mut best_index = 0;
+        for (j, score) in scores.iter().enumerate() {
+            if *score > scores[best_index] {
+                best_index = j;
+            }
+        }
+        let mut res = vec![String::new(); words.len()];
+        for i in (0..words.len()).rev() {
+            res[i] = candidates[i][best_index].clone();
+            if i > 0 {
+                best_index = back_pointers[i][best_index];
+            }
+        }
+        res
+    }
+
+    // Restores the sentence and writes it with the text before each word.
+    fn flush_sentence(&self, text: &str, sentence: &mut Vec<(usize, usize)>, res: &mut String, last_end: &mut usize) {
+        let lower_case_words: Vec<String> = sentence.iter()
+            .map(|(start, end)| casing::case_fold(&text[*start..*end]))
+            .collect();
+        let restored = self.restore_words(&lower_case_words);
+        for ((start, end), new_word) in sentence.iter().zip(restored.iter()) {
+            res.push_str(&text[*last_end..*start]);
+            res.push_str(&apply_case_of(&text[*start..*end], new_word));
+            *last_end = *end;
+        }
+        sentence.clear();
+    }
+
+    // Restores the diacritics of the text, keeps everything between the
+    // words and the case of each word.
+    pub fn restore_text(&self, text: &str) -> String {
+        let mut res = String::with_capacity(text.len() + text.len() / 10);
+        // End of the text already written.
+        let mut last_end = 0;
+        // Words of the current sentence, (start, end) in the text.
+        let mut sentence: Vec<(usize, usize)> = Vec::new();
+        let mut prev_word_end = 0;
+
+        for word_match in self.reg_ex_pt.find_iter(text) {
+            let gap = &text[prev_word_end..word_match.start()];
+            if gap.contains(['.', '!', '?', '\n']) {
+                self.flush_sentence(text, &mut sentence, &mut res, &mut last_end);
+            }
+            sentence.push((word_match.start(), word_match.end()));
+            prev_word_end = word_match.end();
+        }
+        self.flush_sentence(text, &mut sentence, &mut res, &mut last_end);
+        res.push_str(&text[last_end..]);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn freq(pairs: &[(&str, u64)]) -> HashMap<String, u64> {
+        pairs.iter().map(|(key, count)| (key.to_string(), *count)).collect()
+    }
+
+    fn restorer(word_freq: &[(&str, u64)], dic_2_grams: &[(&str, u64)]) -> DiacriticRestorer {
+        DiacriticRestorer::new(&freq(word_freq), &freq(dic_2_grams), AccentConfusion::default_confusion())
+    }
+
+    #[test]
+    fn restores_a_sentence() {
+        let restorer = restorer(
+            &[("não", 50), ("é", 40), ("e", 60), ("possível", 10)],
+            &[("não é", 20), ("é possível", 8), ("e não", 5)]);
+        assert_eq!(restorer.restore_text("nao e possivel"), "não é possível");
+        // The case of each word and the text between them are kept.
+        assert_eq!(restorer.restore_text("Nao e possivel!\n"), "Não é possível!\n");
+    }
+
+    #[test]
+    fn the_context_chooses_the_candidate() {
+        let restorer = restorer(
+            &[("sábia", 10), ("sabiá", 10), ("mulher", 20), ("ave", 20)],
+            &[("mulher sábia", 5), ("ave sabiá", 5)]);
+        assert_eq!(restorer.candidates("sabia"), vec!["sabia", "sábia", "sabiá"]);
+        assert_eq!(restorer.restore_text("a mulher sabia"), "a mulher sábia");
+        assert_eq!(restorer.restore_text("a ave sabia"), "a ave sabiá");
+    }
+
+    #[test]
+    fn unknown_words_are_kept() {
+        let restorer = restorer(&[("não", 5)], &[]);
+        assert_eq!(restorer.restore_text("xpto, nao"), "xpto, não");
+        assert_eq!(restorer.restore_text(""), "");
+    }
+}
@@ -0,0 +1,34 @@
+/// Readers for the frequency files generated by this project, the
+/// "dic_corpus_unique*.words" with lines "word count" and the
+/// "2_grams*.words" with lines "word_1 word_2 count".
+///
+
+use std::fs;
+use std::collections::HashMap;
+
+// Parses one line "key count", the key can have spaces (2 grams).
+pub fn parse_freq_line(line: &str) -> Option<(&str, u64)> {
+    let (key, count_str) = line.trim_end().rsplit_once(' ')?;
+    let count = count_str.parse::<u64>().ok()?;
+    if key.is_empty() {
+        return None;
+    }
+    Some((key, count))
+}
+
+pub fn parse_freq_text(text: &str) -> HashMap<String, u64> {
+    let mut dic_freq: HashMap<String, u64> = HashMap::new();
+    for line in text.lines() {
+        if let Some((key, count)) = parse_freq_line(line) {
+            *dic_freq.entry(key.to_string()).or_insert(0) += count;
+        }
+    }
+    dic_freq
+}
+
+// Loads a "dic_corpus_unique*.words" or a "2_grams*.words" file.
+pub fn load_freq_file(path: &str) -> Result<HashMap<String, u64>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| format!("{}: {}", path, err))?;
+    Ok(parse_freq_text(&text))
+}
@@ -0,0 +1,224 @@
+/// Counters of words and n grams keyed by integer ids instead of strings.
+///
+/// Each word is interned once in a Vocabulary (word <-> u32 id), and the
+/// n grams are counted with fixed size arrays of ids, [u32; N], so counting
+/// a 2 grams doesn't build the string "prev_word word" and the key is 8
+/// bytes. The strings are made only when the counts are written.
+///
+///     let mut vocabulary = Vocabulary::new();
+///     let mut counter: IdCounter<2> = IdCounter::new();
+///     counter.add([vocabulary.intern("da"), vocabulary.intern("comissão")]);
+///     counter.to_string_map(&vocabulary)    -> {"da comissão": 1}
+///
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+
+// Each string is kept once, in the text of all the words, and the table
+// of the ids is an open addressing table of u32, so a word costs its bytes,
+// its end in the text and 2 to 4 slots.
+#[derive(Debug, Default, Clone)]
+pub struct Vocabulary {
+    // The words one after the other, the word of an id ends at ends[id].
+    text: String,
+    ends: Vec<usize>,
+    // Id + 1 of the words by hash, linear probing, 0 is an empty slot. The
+    // length is a power of 2, at most half full.
+    slots: Vec<u32>,
+}
+
+impl Vocabulary {
+    pub fn new() -> Self {
+        Vocabulary::default()
+    }
+
+    fn hash_of(word: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        word.hash(&mut hasher);
+        hasher.finish() as usize
+    }
+
+    // The slot of the word, or the empty slot where it goes.
+    fn find_slot(&self, word: &str) -> usize {
+        let mask = self.slots.len() - 1;
+        let mut slot = Vocabulary::hash_of(word) & mask;
+        while self.slots[slot] != 0 && self.word(self.slots[slot] - 1) != word {
+            slot = (slot + 1) & mask;
+        }
+        slot
+    }
+
+    fn grow(&mut self) {
+        let num_slots = (self.slots.len() * 2).max(16);
+        self.slots = vec![0; num_slots];
+        for id in 0..self.ends.len() as u32 {
+            let slot = self.find_slot(self.word(id));
+            self.slots[slot] = id + 1;
+        }
+    }
+
+    // The id of the word, a new one the first time the word is seen.
+    pub fn intern(&mut self, word: &str) -> u32 {
+        if (self.ends.len() + 1) * 2 > self.slots.len() {
+            self.grow();
+        }
+        let slot = self.find_slot(word);
+        if self.slots[slot] != 0 {
+            return self.slots[slot] - 1;
+        }
+        let id = self.ends.len() as u32;
+        self.text.push_str(word);
+        self.ends.push(self.text.len());
+        self.slots[slot] = id + 1;
+        id
+    }
+
+    pub fn word(&self, id: u32) -> &str {
+        let id = id as usize;
+        let start = if id == 0 { 0 } else { self.ends[id - 1] };
+        &self.text[start..self.ends[id]]
+    }
+
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    // Heap bytes of the vocabulary.
+    pub fn num_bytes(&self) -> usize {
+        self.text.capacity() + self.ends.capacity() * std::mem::size_of::<usize>() + self.slots.capacity() * 4
+    }
+
+    // The bytes of the key of the ids, the words separated by spaces.
+    fn key_bytes<'a>(&'a self, ids: &'a [u32]) -> impl Iterator<Item = u8> + 'a {
+        ids.iter().enumerate().flat_map(move |(i, id)| {
+            let separator = if i > 0 { Some(b' ') } else { None };
+            separator.into_iter().chain(self.word(*id).bytes())
+        })
+    }
+
+    // The order of the keys of the ids, the byte order of the text files,
+    // without making the strings.
+    pub fn cmp_keys(&self, ids_1: &[u32], ids_2: &[u32]) -> Ordering {
+        self.key_bytes(ids_1).cmp(self.key_bytes(ids_2))
+    }
+
+    // Writes the key of the ids, without making the string.
+    pub fn write_key(&self, out: &mut dyn Write, ids: &[u32]) -> io::Result<()> {
+        for (i, id) in ids.iter().enumerate() {
+            if i > 0 {
+                out.write_all(b" ")?;
+            }
+            out.write_all(self.word(*id).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    // The words of the ids separated by spaces, the key of the text files.
+    pub fn make_key(&self, ids: &[u32]) -> String {
+        let mut key = String::new();
+        for (i, id) in ids.iter().enumerate() {
+            if i > 0 {
+                key.push(' ');
+            }
+            key.push_str(self.word(*id));
+        }
+        key
+    }
+}
+
+// Counts of the n grams of N words.
+#[derive(Debug, Clone)]
+pub struct IdCounter<const N: usize> {
+    pub counts: HashMap<[u32; N], u64>,
+}
+
+impl<const N: usize> Default for IdCounter<N> {
+    fn default() -> Self {
+        IdCounter { counts: HashMap::new() }
+    }
+}
+
+impl<const N: usize> IdCounter<N> {
+    pub fn new() -> Self {
+        IdCounter::default()
+    }
+
+    pub fn add(&mut self, ids: [u32; N]) {
+        self.add_count(ids, 1);
+    }
+
+    pub fn add_count(&mut self, ids: [u32; N], count: u64) {
+        *self.counts.entry(ids).or_insert(0) += count;
+    }
+
+    // Takes the counts, sorted in the order of the text files. The table
+    // is freed while the Vec is filled.
+    pub fn take_sorted(&mut self, vocabulary: &Vocabulary) -> Vec<([u32; N], u64)> {
+        let mut counts: Vec<([u32; N], u64)> = std::mem::take(&mut self.counts).into_iter().collect();
+        counts.sort_unstable_by(|(ids_1, _), (ids_2, _)| vocabulary.cmp_keys(ids_1, ids_2));
+        counts
+    }
+
+    // The counts with the string keys, in the order of the text files.
+    pub fn to_string_map(&self, vocabulary: &Vocabulary) -> BTreeMap<String, u64> {
+        self.counts.iter()
+            .map(|(ids, count)| (vocabulary.make_key(ids), *count))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern_gives_one_id_for_each_word() {
+        let mut vocabulary = Vocabulary::new();
+        let words: Vec<String> = (0..1000).map(|i| format!("palavra{}", i % 300)).collect();
+        let ids: Vec<u32> = words.iter().map(|word| vocabulary.intern(word)).collect();
+        assert_eq!(vocabulary.len(), 300);
+        for (word, id) in words.iter().zip(&ids) {
+            assert_eq!(vocabulary.word(*id), word);
+            assert_eq!(vocabulary.intern(word), *id);
+        }
+        // The empty word is a word too.
+        let empty_id = vocabulary.intern("");
+        assert_eq!((vocabulary.word(empty_id), vocabulary.len()), ("", 301));
+    }
+
+    #[test]
+    fn take_sorted_in_the_byte_order_of_the_keys() {
+        let mut vocabulary = Vocabulary::new();
+        let mut counter: IdCounter<2> = IdCounter::new();
+        let pairs = [("ab", "c"), ("a", "b"), ("a", "bc"), ("é", "a"), ("a", "b"), ("z", "a")];
+        for (word_1, word_2) in pairs {
+            counter.add([vocabulary.intern(word_1), vocabulary.intern(word_2)]);
+        }
+        let expected: Vec<(String, u64)> = counter.to_string_map(&vocabulary).into_iter().collect();
+        let mut lines: Vec<u8> = Vec::new();
+        let sorted: Vec<(String, u64)> = counter.take_sorted(&vocabulary).into_iter()
+            .map(|(ids, count)| {
+                vocabulary.write_key(&mut lines, &ids).unwrap();
+                (vocabulary.make_key(&ids), count)
+            })
+            .collect();
+        assert_eq!(sorted, expected);
+        assert_eq!(String::from_utf8(lines).unwrap(), "a ba bcab cz aé a");
+        assert!(counter.counts.is_empty());
+    }
+
+    #[test]
+    fn string_map_of_the_counts() {
+        let mut vocabulary = Vocabulary::new();
+        let mut counter: IdCounter<2> = IdCounter::new();
+        let (da, comissão, união) = (vocabulary.intern("da"), vocabulary.intern("comissão"), vocabulary.intern("união"));
+        counter.add([da, comissão]);
+        counter.add([da, união]);
+        counter.add_count([da, comissão], 2);
+        let counts: Vec<(String, u64)> = counter.to_string_map(&vocabulary).into_iter().collect();
+        assert_eq!(counts, vec![("da comissão".to_string(), 3), ("da união".to_string(), 1)]);
+    }
+}
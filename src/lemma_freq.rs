@@ -0,0 +1,141 @@
+/// Lemma frequencies and lemma 2 grams, counted over the corrected words
+/// with the lemmas of the HunSpell analysis (see morph.rs).
+///
+/// A word can have more then one lemma, ex: "casa" -> "casa" (noun) and
+/// "casar" (verb). The ambiguous words are counted in one of two ways:
+///
+///     Split   each lemma gets one count, "casa 1" and "casar 1", the 2 grams
+///             get one count for each pair of lemmas.
+///     Flag    the lemmas are joined in one key, "casa|casar 1".
+///
+/// In both modes the ambiguous words are also counted in a separate table
+/// with lines "word -> lemma_1 lemma_2 count", to review them.
+///
+/// The counts are made from the final words and 2 grams tables, so each
+/// distinct word is analyzed once.
+///
+
+use std::collections::BTreeMap;
+
+use crate::morph::MorphAnalyzer;
+use crate::spell_checker::SpellChecker;
+
+pub const LEMMA_SEPARATOR: &str = "|";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LemmaMode {
+    Split,
+    Flag,
+}
+
+impl LemmaMode {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "split" => Some(LemmaMode::Split),
+            "flag" => Some(LemmaMode::Flag),
+            _ => None,
+        }
+    }
+}
+
+pub struct LemmaCounter {
+    pub mode: LemmaMode,
+    analyzer: MorphAnalyzer,
+    pub lemma_freq: BTreeMap<String, u64>,
+    pub lemma_2_grams_freq: BTreeMap<String, u64>,
+    pub ambiguous_freq: BTreeMap<String, u64>,
+}
+
+impl LemmaCounter {
+    pub fn new(mode: LemmaMode) -> Self {
+        LemmaCounter {
+            mode,
+            analyzer: MorphAnalyzer::new(),
+            lemma_freq: BTreeMap::new(),
+            lemma_2_grams_freq: BTreeMap::new(),
+            ambiguous_freq: BTreeMap::new(),
+        }
+    }
+
+    // The lemma keys of the word for the mode, one key in the Flag mode.
+    pub fn lemma_keys(&mut self, spell_checker: &dyn SpellChecker, word: &str) -> Vec<String> {
+        let lemmas = self.analyzer.lemmas(spell_checker, word);
+        match self.mode {
+            LemmaMode::Split => lemmas.to_vec(),
+            LemmaMode::Flag => vec![lemmas.join(LEMMA_SEPARATOR)],
+        }
+    }
+
+    // Adds the count of one corrected word to its lemmas.
+    pub fn add_word(&mut self, spell_checker: &dyn SpellChecker, word: &str, count: u64) {
+        let lemmas = self.analyzer.lemmas(spell_checker, word);
+        if lemmas.len() > 1 {
+            let key = word.to_string() + " -> " + &lemmas.join(" ");
+            *self.ambiguous_freq.entry(key).or_insert(0) += count;
+        }
+        for lemma_key in self.lemma_keys(spell_checker, word) {
+            *self.lemma_freq.entry(lemma_key).or_insert(0) += count;
+        }
+    }
+
+    // Adds the count of the 2 grams of two corrected words to the lemma 2 grams.
+    pub fn add_2_grams(&mut self, spell_checker: &dyn SpellChecker, word_1: &str, word_2: &str, count: u64) {
+        let lemma_keys_1 = self.lemma_keys(spell_checker, word_1);
+        let lemma_keys_2 = self.lemma_keys(spell_checker, word_2);
+        for lemma_1 in &lemma_keys_1 {
+            for lemma_2 in &lemma_keys_2 {
+                let key = lemma_1.to_string() + " " + lemma_2;
+                *self.lemma_2_grams_freq.entry(key).or_insert(0) += count;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::morph::tests::FakeAnalyzer;
+
+    #[test]
+    fn parse_modes() {
+        assert_eq!(LemmaMode::parse("split"), Some(LemmaMode::Split));
+        assert_eq!(LemmaMode::parse("flag"), Some(LemmaMode::Flag));
+        assert_eq!(LemmaMode::parse("Split"), None);
+    }
+
+    fn fake() -> FakeAnalyzer {
+        FakeAnalyzer::new(
+            &[("casa", " st:casa po:nc"), ("casa", " st:casar po:v"), ("casas", " st:casa po:nc"),
+              ("bela", " st:belo po:adj")],
+            &[])
+    }
+
+    fn freq(pairs: &[(&str, u64)]) -> BTreeMap<String, u64> {
+        pairs.iter().map(|(key, count)| (key.to_string(), *count)).collect()
+    }
+
+    #[test]
+    fn split_gives_a_count_to_each_lemma() {
+        let fake = fake();
+        let mut lemma_counter = LemmaCounter::new(LemmaMode::Split);
+        lemma_counter.add_word(&fake, "casa", 3);
+        lemma_counter.add_word(&fake, "casas", 2);
+        lemma_counter.add_2_grams(&fake, "casa", "bela", 4);
+        assert_eq!(lemma_counter.lemma_freq, freq(&[("casa", 5), ("casar", 3)]));
+        assert_eq!(lemma_counter.lemma_2_grams_freq, freq(&[("casa belo", 4), ("casar belo", 4)]));
+        assert_eq!(lemma_counter.ambiguous_freq, freq(&[("casa -> casa casar", 3)]));
+    }
+
+    #[test]
+    fn flag_joins_the_lemmas() {
+        let fake = fake();
+        let mut lemma_counter = LemmaCounter::new(LemmaMode::Flag);
+        lemma_counter.add_word(&fake, "casa", 3);
+        lemma_counter.add_word(&fake, "casas", 2);
+        lemma_counter.add_word(&fake, "casa", 1);
+        lemma_counter.add_2_grams(&fake, "bela", "casa", 4);
+        assert_eq!(lemma_counter.lemma_freq, freq(&[("casa", 2), ("casa|casar", 4)]));
+        assert_eq!(lemma_counter.lemma_2_grams_freq, freq(&[("belo casa|casar", 4)]));
+        assert_eq!(lemma_counter.ambiguous_freq, freq(&[("casa -> casa casar", 4)]));
+    }
+}
@@ -0,0 +1,116 @@
+/// Evaluation of a language model (see ngram_lm.rs) on held-out sentences,
+/// tokenized and corrected with the same pipeline of the training corpus.
+///
+/// The words out of the vocabulary of the model (OOV) are scored as <unk>,
+/// as are the <unk> of the pipeline (the words rejected or without a
+/// correction), and each sentence ends with </s>, so the perplexity is:
+///
+///     PPL = 10 ^ (-log10 P(sentences) / (num_words + num_sentences))
+///
+/// The report has the log10 probability of each sentence, a tab separated
+/// file with the columns log10_prob, num_words, num_oov and sentence.
+///
+
+use std::fs;
+
+use crate::ngram_lm::{self, LanguageModel};
+
+#[derive(Debug, Clone)]
+pub struct SentenceScore {
+    pub log_prob: f64,
+    pub num_words: u64,
+    pub num_oov: u64,
+    pub sentence: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Evaluation {
+    pub num_sentences: u64,
+    pub num_words: u64,
+    pub num_oov: u64,
+    pub log_prob: f64,
+    pub sentence_scores: Vec<SentenceScore>,
+}
+
+impl Evaluation {
+    pub fn perplexity(&self) -> f64 {
+        let num_events = (self.num_words + self.num_sentences).max(1) as f64;
+        10f64.powf(-self.log_prob / num_events)
+    }
+
+    pub fn oov_rate(&self) -> f64 {
+        self.num_oov as f64 / self.num_words.max(1) as f64
+    }
+
+    pub fn make_string(&self) -> String {
+        let mut res = String::from("log10_prob\tnum_words\tnum_oov\tsentence\n");
+        for score in &self.sentence_scores {
+            res.push_str(&format!("{:.4}\t{}\t{}\t{}\n", score.log_prob, score.num_words, score.num_oov, score.sentence));
+        }
+        res
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.make_string())
+            .map_err(|err| format!("{}: {}", path, err))
+    }
+}
+
+pub fn score_sentence(language_model: &LanguageModel, words: &[String]) -> SentenceScore {
+    let mut context: Vec<String> = vec![ngram_lm::SENTENCE_START.to_string()];
+    let mut log_prob = 0.0;
+    let mut num_oov = 0;
+    for word in words {
+        // The pipeline writes <unk> for the words it couldn't correct, the
+        // model has <unk> in the vocabulary, but they are OOV too.
+        let word = if word != ngram_lm::UNKNOWN_WORD && language_model.contains_word(word) {
+            word.as_str()
+        } else {
+            num_oov += 1;
+            ngram_lm::UNKNOWN_WORD
+        };
+        log_prob += language_model.log_prob(&context, word);
+        context.push(word.to_string());
+    }
+    log_prob += language_model.log_prob(&context, ngram_lm::SENTENCE_END);
+
+    SentenceScore {
+        log_prob,
+        num_words: words.len() as u64,
+        num_oov,
+        sentence: words.join(" "),
+    }
+}
+
+pub fn evaluate(language_model: &LanguageModel, sentences: &[Vec<String>]) -> Evaluation {
+    let mut evaluation = Evaluation::default();
+    for words in sentences {
+        let score = score_sentence(language_model, words);
+        evaluation.num_sentences += 1;
+        evaluation.num_words += score.num_words;
+        evaluation.num_oov += score.num_oov;
+        evaluation.log_prob += score.log_prob;
+        evaluation.sentence_scores.push(score);
+    }
+    evaluation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARPA: &str = "\\data\\\nngram 1=4\n\n\\1-grams:\n-0.5\t<s>\n-0.5\t</s>\n-1.0\t<unk>\n-0.3\tcasa\n\n\\end\\\n";
+
+    fn words(text: &str) -> Vec<String> {
+        text.split_whitespace().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn pipeline_unk_is_oov() {
+        let language_model = LanguageModel::parse_arpa(ARPA).unwrap();
+        assert!(language_model.contains_word(ngram_lm::UNKNOWN_WORD));
+        let score = score_sentence(&language_model, &words("casa <unk> carro"));
+        assert_eq!((score.num_words, score.num_oov), (3, 2));
+        assert!((score.log_prob - (-0.3 - 1.0 - 1.0 - 0.5)).abs() < 1e-9);
+    }
+}
@@ -57,8 +57,60 @@
 
 
 mod strings_extender;
-
+mod ortho_rules;
+mod freq_dic;
+mod word_corrector;
+mod context_rescore;
+mod correction_audit;
+mod overrides;
+mod review_tui;
+mod accent_confusion;
+mod diacritic_restore;
+mod text_convert;
+mod casing;
+mod truecase;
+mod morph;
+mod lemma_freq;
+mod pos_n_grams;
+mod affix_dic;
+mod symspell;
+mod word_segment;
+mod symspell_export;
+mod ngram_lm;
+mod lm_evaluate;
+mod next_word;
+mod mmap_file;
+mod autocomplete;
+mod ngram_store;
+mod interned_counter;
+mod ngram_counter;
+mod spill_counter;
+mod approx_counter;
+mod spell_checker;
+
+#[cfg(feature = "hunspell")]
 use hunspell_rs::Hunspell;
+use ortho_rules::{OrthoRuleSet, RankedCorrection};
+use word_corrector::{WordCorrector, Correction};
+use context_rescore::ContextRescorer;
+use correction_audit::CorrectionAudit;
+use overrides::{Overrides, OverrideFiles};
+use accent_confusion::AccentConfusion;
+use diacritic_restore::DiacriticRestorer;
+use casing::{CasedWord, CasePattern};
+use truecase::{TruecaseCounter, Truecaser};
+use lemma_freq::{LemmaCounter, LemmaMode};
+use pos_n_grams::PosNGramCounter;
+use affix_dic::NativeDictionary;
+use spell_checker::SpellChecker;
+use symspell::{SymSpell, Verbosity};
+use word_segment::WordSegmenter;
+use ngram_lm::{NGramCounts, LanguageModel, Smoothing};
+use next_word::NextWordPredictor;
+use autocomplete::AutocompleteIndex;
+use ngram_store::NGramStore;
+use interned_counter::{IdCounter, Vocabulary};
+use ngram_counter::{CountingMode, NGramCounter};
 use strings_extender::{StringUtils, StringUtilsSlices};
 use std::{fs, collections::HashMap};    // Readfile
 
@@ -81,6 +133,13 @@ fn main() {
     println!("**  Generation of word digrams (2 grams) pt_PT in Rust  **");
     println!("**********************************************************");
 
+    // Sub commands, without arguments runs the generation below.
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 {
+        run_sub_command(&args[1..]);
+        return;
+    }
+
     // test_hunspell();
 
     // NOTE: Use only in the beginning to process small file from a big txt file.
@@ -114,14 +173,628 @@ fn main() {
     println!("  res = {}", res_1);
 }
 
-fn get_hunspell() -> Hunspell {
+fn print_usage() {
+    println!("\nUsage:");
+    println!("   hunspell                   Generates the frequency files (see main).");
+    println!("   hunspell review [not_check_dic_file] [corpus_file]");
+    println!("                              Interactive review of the rejected words.");
+    println!("   hunspell restore <in_text_file> [out_text_file] [dic_file] [2_grams_file]");
+    println!("                              Restores the diacritics of text written without them.");
+    println!("   hunspell convert <in_text_file> <out_text_file> [dic_file]");
+    println!("                              Converts a text to the new orthographic treaty (AO90).");
+    println!("   hunspell truecase <in_text_file> [out_text_file] [truecase_file]");
+    println!("                              Restores the case of lower case text.");
+    println!("   hunspell expand [out_words_file]");
+    println!("                              Writes all the forms of the HunSpell dictionary, without the C library.");
+    println!("   hunspell check <word> [word ...]");
+    println!("                              Checks the words without the C library.");
+    println!("   hunspell symspell <word_or_text> [dic_file] [2_grams_file] [--verbosity=top|closest|all]");
+    println!("                              SymSpell suggestions of a word, or the correction of a text.");
+    println!("   hunspell segment <text> [dic_file] [2_grams_file]");
+    println!("                              Splits text without spaces in words, with the 2 grams file rescores the split.");
+    println!("   hunspell export_symspell [dic_file] [2_grams_file] [out_dic_file] [out_2_grams_file]");
+    println!("                              Writes and verifies the SymSpell frequency and bigram dictionaries.");
+    println!("   hunspell arpa <corpus_file> <out_arpa_file> [order] [kn|wb] [dic_file]");
+    println!("                              Writes an n grams language model in the ARPA format.");
+    println!("   hunspell split <corpus_file> <out_train_file> <out_held_out_file> [held_out_every_n]");
+    println!("                              Splits the sentences of the corpus, one in each n is held-out.");
+    println!("   hunspell evaluate <held_out_file> <arpa_file> [dic_file]");
+    println!("                              Perplexity, OOV rate and log probability of each sentence.");
+    println!("   hunspell predict <text> [k] [dic_file] [2_grams_file]");
+    println!("                              Next words of the text, or completions of its last word if it doesn't end in a space.");
+    println!("   hunspell autocomplete_build [dic_file] [out_index_file]");
+    println!("                              Writes the binary prefix autocomplete index.");
+    println!("   hunspell autocomplete <prefix> [k] [exact|fold] [index_file]");
+    println!("                              Most frequent words that start with the prefix, fold ignores the accents.");
+    println!("   hunspell ngram_store_build [dic_file] [2_grams_file] [out_store_file]");
+    println!("                              Writes the binary store of the words and the 2 grams counts.");
+    println!("   hunspell ngram_store <store_file> <word_1> [word_2]");
+    println!("                              Counts of the word and of the 2 grams, or the words after word_1.");
+    println!("   hunspell count_words [corpus_file] [out_dic_file] [dic_file] [--lemma=split|flag] [--spill[=bytes]]");
+    println!("                              [--approx[=num_top,epsilon,delta]] [--symspell]");
+    println!("                              Generates the words frequency files, --lemma also counts the lemmas.");
+    println!("   hunspell count_2_grams [corpus_file] [out_2_grams_file] [dic_file] [--context] [--lemma=split|flag] [--pos]");
+    println!("                              [--spill[=bytes]] [--approx[=num_top,epsilon,delta]] [--symspell]");
+    println!("                              Generates the 2 grams files, --context re-scores the ambiguous words with the 2 grams,");
+    println!("                              --pos also counts the part of speech n grams.");
+    println!("                              --spill counts with a memory budget (ex: 512M, default 256M) and runs on disk, not with --context or --lemma,");
+    println!("                              --approx writes only the top k counts (default 100000,0.00001,0.001), not with --context,");
+    println!("                              --symspell takes the suggestions from SymSpell instead of HunSpell.");
+}
+
+// Splits the arguments of a sub command in the positional ones and the
+// options "--name" or "--name=value", the options must be in known.
+fn split_options(args: &[String], known: &[&str]) -> Result<(Vec<String>, HashMap<String, String>), String> {
+    let mut positional: Vec<String> = Vec::new();
+    let mut options: HashMap<String, String> = HashMap::new();
+    for arg in args {
+        match arg.strip_prefix("--") {
+            Some(option) => {
+                let (name, value) = option.split_once('=').unwrap_or((option, ""));
+                if !known.contains(&name) {
+                    return Err(format!("unknown option \"{}\"", arg));
+                }
+                options.insert(name.to_string(), value.to_string());
+            },
+            None => positional.push(arg.clone()),
+        }
+    }
+    Ok((positional, options))
+}
+
+// The options of the count_words and count_2_grams sub commands.
+struct CountOptions {
+    paths: Vec<String>,
+    flag_context_pass: bool,
+    lemma_mode: Option<LemmaMode>,
+    flag_pos_n_grams: bool,
+    flag_symspell: bool,
+    counting_mode: CountingMode,
+}
+
+impl CountOptions {
+    fn parse(args: &[String], known: &[&str]) -> Result<Self, String> {
+        let (paths, options) = split_options(args, known)?;
+        let lemma_mode = match options.get("lemma") {
+            Some(name) => Some(LemmaMode::parse(name)
+                .ok_or_else(|| format!("unknown lemma mode \"{}\", split or flag", name))?),
+            None => None,
+        };
+        // The context pass and the lemmas read all the counts to memory.
+        if options.contains_key("spill") && (options.contains_key("context") || lemma_mode.is_some()) {
+            return Err("--spill can't be used with --context or --lemma".to_string());
+        }
+        // The context pass writes exact counts over the top k.
+        if options.contains_key("approx") && options.contains_key("context") {
+            return Err("--approx can't be used with --context".to_string());
+        }
+        let counting_mode = match (options.get("spill"), options.get("approx")) {
+            (Some(_), Some(_)) => return Err("--spill and --approx can't be used together".to_string()),
+            (Some(budget), None) => CountingMode::Spill { memory_budget: ngram_counter::parse_memory_budget(budget)? },
+            (None, Some(values)) => ngram_counter::parse_approximate(values)?,
+            (None, None) => CountingMode::InMemory,
+        };
+        Ok(CountOptions {
+            paths,
+            flag_context_pass: options.contains_key("context"),
+            lemma_mode,
+            flag_pos_n_grams: options.contains_key("pos"),
+            flag_symspell: options.contains_key("symspell"),
+            counting_mode,
+        })
+    }
+
+    fn path(&self, i: usize, default: &str) -> String {
+        self.paths.get(i).cloned().unwrap_or_else(|| default.to_string())
+    }
+}
+
+fn run_sub_command(args: &[String]) {
+    let arg = |i: usize, default: &str| -> String {
+        args.get(i).cloned().unwrap_or_else(|| default.to_string())
+    };
+
+    match args[0].as_str() {
+        "review" => {
+            let not_check_path = arg(1, "/dev/shm/not_check_dic_corpus_unique_small.words");
+            let corpus_path = arg(2, "./data/small_europarl-v7.pt-en.pt");
+            let reg_ex_pt = LangRegEx::new(Lang::PT).reg_ex_comp_word_pattern;
+            review_tui::review_not_check_file(&not_check_path, &corpus_path, &reg_ex_pt, get_override_files());
+        },
+        "restore" if args.len() > 1 => {
+            let in_text_path = &args[1];
+            let out_text_path = args.get(2);
+            let in_word_freq_path = arg(3, "./data/dic_corpus_unique_small.words");
+            let in_2_grams_path = arg(4, "./data/2_grams_small.words");
+            restore_diacritics_of_file(in_text_path, out_text_path.map(|path| path.as_str()), &in_word_freq_path, &in_2_grams_path);
+        },
+        "convert" if args.len() > 2 => {
+            let in_word_freq_path = arg(3, "./data/dic_corpus_unique_small.words");
+            convert_text_of_file(&args[1], &args[2], &in_word_freq_path);
+        },
+        "truecase" if args.len() > 1 => {
+            let in_truecase_path = arg(3, "/dev/shm/truecase_dic_corpus_unique_small.words");
+            truecase_text_of_file(&args[1], args.get(2).map(|path| path.as_str()), &in_truecase_path);
+        },
+        "expand" => {
+            let out_words_path = arg(1, "/dev/shm/pt_PT_full_forms.words");
+            let native_dic = get_native_dictionary();
+            let _res = fs::write(&out_words_path, native_dic.make_string());
+            println!("\nForms: {}", native_dic.words.len());
+            println!("\nFile written: {}", &out_words_path);
+        },
+        "check" if args.len() > 1 => {
+            let native_dic = get_native_dictionary();
+            for word in &args[1..] {
+                println!("   {}: {}", word, native_dic.check(word));
+            }
+        },
+        "symspell" if args.len() > 1 => {
+            let (paths, options) = match split_options(&args[1..], &["verbosity"]) {
+                Ok(res) => res,
+                Err(err) => {
+                    println!("\nError: {}", err);
+                    print_usage();
+                    return;
+                },
+            };
+            let verbosity = match options.get("verbosity") {
+                Some(name) => match Verbosity::parse(name) {
+                    Some(verbosity) => verbosity,
+                    None => {
+                        println!("\nError: unknown verbosity \"{}\", top, closest or all", name);
+                        return;
+                    },
+                },
+                None => Verbosity::All,
+            };
+            let path = |i: usize, default: &str| paths.get(i).cloned().unwrap_or_else(|| default.to_string());
+            let in_word_freq_path = path(1, "./data/dic_corpus_unique_small.words");
+            let in_2_grams_path = path(2, "./data/2_grams_small.words");
+            match paths.first() {
+                Some(input) => symspell_lookup(input, &in_word_freq_path, &in_2_grams_path, verbosity),
+                None => print_usage(),
+            }
+        },
+        "segment" if args.len() > 1 => {
+            let in_word_freq_path = arg(2, "./data/dic_corpus_unique_small.words");
+            let word_segmenter = WordSegmenter::from_files(&in_word_freq_path, args.get(3).map(|path| path.as_str()))
+                .expect("Something went wrong reading the frequency files");
+            println!("\n   {}", word_segmenter.segment_text(&args[1]));
+        },
+        "export_symspell" => {
+            let in_word_freq_path = arg(1, "./data/dic_corpus_unique_small.words");
+            let in_2_grams_path = arg(2, "./data/2_grams_small.words");
+            let out_word_freq_path = arg(3, "/dev/shm/symspell_frequency_dictionary_pt_PT.txt");
+            let out_2_grams_path = arg(4, "/dev/shm/symspell_bigram_dictionary_pt_PT.txt");
+            export_symspell_files(&in_word_freq_path, &in_2_grams_path, &out_word_freq_path, &out_2_grams_path);
+        },
+        "arpa" if args.len() > 2 => {
+            let order = match arg(3, "3").parse::<usize>() {
+                Ok(order) if order >= 1 => order,
+                _ => {
+                    println!("\nError: the order \"{}\" isn't a number >= 1", arg(3, "3"));
+                    return;
+                },
+            };
+            let smoothing = match Smoothing::parse(&arg(4, "kn")) {
+                Some(smoothing) => smoothing,
+                None => {
+                    println!("\nError: unknown smoothing \"{}\", kn or wb", arg(4, "kn"));
+                    return;
+                },
+            };
+            let in_word_freq_path = arg(5, "./data/dic_corpus_unique_small.words");
+            write_arpa_of_file(&args[1], &args[2], order, smoothing, &in_word_freq_path);
+        },
+        "split" if args.len() > 3 => {
+            let held_out_every_n = arg(4, "10").parse::<usize>().unwrap_or(10).max(2);
+            split_corpus_file(&args[1], &args[2], &args[3], held_out_every_n);
+        },
+        "evaluate" if args.len() > 2 => {
+            let in_word_freq_path = arg(3, "./data/dic_corpus_unique_small.words");
+            evaluate_language_model(&args[1], &args[2], &in_word_freq_path);
+        },
+        "predict" if args.len() > 1 => {
+            let k = arg(2, "5").parse::<usize>().unwrap_or(5);
+            let in_word_freq_path = arg(3, "./data/dic_corpus_unique_small.words");
+            let in_2_grams_path = arg(4, "./data/2_grams_small.words");
+            let predictor = NextWordPredictor::from_files(&in_word_freq_path, &[&in_2_grams_path])
+                .expect("Something went wrong reading the frequency files");
+            let lower_case_text = casing::case_fold(&args[1]);
+            let mut words: Vec<&str> = lower_case_text.split_whitespace().collect();
+            let predictions = if lower_case_text.ends_with(' ') || words.is_empty() {
+                predictor.top_k_next(&words, k)
+            } else {
+                let partial_prefix = words.pop().unwrap();
+                predictor.top_k_completions(words.last().copied(), partial_prefix, k)
+            };
+            for (word, count) in predictions {
+                println!("   {} {}", word, count);
+            }
+        },
+        "autocomplete_build" => {
+            let in_word_freq_path = arg(1, "./data/dic_corpus_unique_small.words");
+            let out_index_path = arg(2, "/dev/shm/autocomplete_small.idx");
+            match autocomplete::build_index_file(&in_word_freq_path, &out_index_path, autocomplete::DEFAULT_MAX_TOP) {
+                Ok(num_bytes) => println!("\nFile written: {}  ({} bytes)", &out_index_path, num_bytes),
+                Err(err) => println!("\nError writing the autocomplete index: {}", err),
+            }
+        },
+        "autocomplete" if args.len() > 1 => {
+            let k = arg(2, "5").parse::<usize>().unwrap_or(5);
+            let flag_fold = arg(3, "fold") == "fold";
+            let in_index_path = arg(4, "/dev/shm/autocomplete_small.idx");
+            // SAFETY: the index is written once by autocomplete_build and
+            // isn't changed while it's open.
+            let index = unsafe { AutocompleteIndex::open(&in_index_path) }
+                .expect("Something went wrong reading the autocomplete index");
+            if k > index.max_top() {
+                println!("\nThe index keeps {} words for each prefix, k = {} gives at most {}.", index.max_top(), k, index.max_top());
+            }
+            let (words, elapsed) = time_it(|| index.top_k(&args[1], k, flag_fold));
+            for (word, count) in words {
+                println!("   {} {}", word, count);
+            }
+            println!("\nLookup: {:.1} us  (memory mapped: {})", elapsed.as_secs_f64() * 1e6, index.is_mapped());
+        },
+        "ngram_store_build" => {
+            let in_word_freq_path = arg(1, "./data/dic_corpus_unique_small.words");
+            let in_2_grams_path = arg(2, "./data/2_grams_small.words");
+            let out_store_path = arg(3, "/dev/shm/2_grams_small.bin");
+            match ngram_store::build_store_file(&in_word_freq_path, &in_2_grams_path, &out_store_path) {
+                Ok(num_bytes) => println!("\nFile written: {}  ({} bytes)", &out_store_path, num_bytes),
+                Err(err) => println!("\nError writing the n grams store: {}", err),
+            }
+        },
+        "ngram_store" if args.len() > 2 => {
+            // SAFETY: the store is written once by ngram_store_build and
+            // isn't changed while it's open.
+            let (store, elapsed) = time_it(|| unsafe { NGramStore::open(&args[1]) });
+            let store = store.expect("Something went wrong reading the n grams store");
+            println!("\nLoaded: {:.1} us  words: {}  2 grams: {}  (memory mapped: {})",
+                     elapsed.as_secs_f64() * 1e6, store.num_words(), store.num_2_grams(), store.is_mapped());
+            println!("   {} {}", &args[2], store.word_count(&args[2]));
+            match args.get(3) {
+                Some(word_2) => println!("   {} {} {}", &args[2], word_2, store.count_2_grams(&args[2], word_2)),
+                None => {
+                    for (word_2, count) in store.next_words(&args[2]) {
+                        println!("   {} {} {}", &args[2], word_2, count);
+                    }
+                },
+            }
+        },
+        "count_words" => {
+            let options = match CountOptions::parse(&args[1..], &["lemma", "spill", "approx", "symspell"]) {
+                Ok(options) => options,
+                Err(err) => {
+                    println!("\nError: {}", err);
+                    print_usage();
+                    return;
+                },
+            };
+            let in_text_corpus_path = options.path(0, "./data/small_europarl-v7.pt-en.pt");
+            let out_dic_unique_words_path = options.path(1, "/dev/shm/dic_corpus_unique_small.words");
+            let in_word_freq_path = options.path(2, "./data/dic_corpus_unique_small.words");
+            read_all_words_freq_of_file(&in_text_corpus_path, &in_word_freq_path, &out_dic_unique_words_path, true,
+                options.lemma_mode, options.flag_symspell, options.counting_mode);
+        },
+        "count_2_grams" => {
+            let options = match CountOptions::parse(&args[1..], &["context", "lemma", "pos", "spill", "approx", "symspell"]) {
+                Ok(options) => options,
+                Err(err) => {
+                    println!("\nError: {}", err);
+                    print_usage();
+                    return;
+                },
+            };
+            let in_text_corpus_path = options.path(0, "./data/small_europarl-v7.pt-en.pt");
+            let out_2_grams_words_path = options.path(1, "/dev/shm/2_grams_small.words");
+            let in_word_freq_path = options.path(2, "./data/dic_corpus_unique_small.words");
+            read_all_2_grams_words_of_file(&in_text_corpus_path, &in_word_freq_path, &out_2_grams_words_path, true,
+                options.flag_context_pass, options.lemma_mode, options.flag_pos_n_grams, options.flag_symspell, options.counting_mode);
+        },
+        _ => print_usage(),
+    }
+}
+
+// Splits the sentences of the corpus in a train file and a held-out file,
+// the same split every time.
+fn split_corpus_file(in_text_corpus_path: &str, out_train_path: &str, out_held_out_path: &str, held_out_every_n: usize) {
+    let input_string = fs::read_to_string(in_text_corpus_path)
+        .expect("Something went wrong reading the file");
+
+    let mut train_string = String::with_capacity(input_string.len());
+    let mut held_out_string = String::with_capacity(input_string.len() / held_out_every_n);
+    let mut num_sentences = 0;
+    for phrase in input_string.split('.') {
+        let phrase = phrase.trim();
+        if phrase.is_empty() {
+            continue;
+        }
+        num_sentences += 1;
+        let out_string = if num_sentences % held_out_every_n == 0 { &mut held_out_string } else { &mut train_string };
+        out_string.push_str(phrase);
+        out_string.push_str(".\n");
+    }
+
+    let _res = fs::write(out_train_path, &train_string);
+    let _res = fs::write(out_held_out_path, &held_out_string);
+    println!("\nSentences: {}  held-out: {}", num_sentences, num_sentences / held_out_every_n);
+    println!("\nFile written: {}", out_train_path);
+    println!("\nFile written: {}", out_held_out_path);
+}
+
+// Scores the held-out sentences with the language model, writes the score
+// of each sentence to the held-out file + ".eval.tsv".
+fn evaluate_language_model(in_held_out_path: &str, in_arpa_path: &str, in_word_freq_path: &str) {
+    let language_model = LanguageModel::from_arpa_file(in_arpa_path)
+        .expect("Something went wrong reading the language model");
+    let sentences = read_corrected_sentences_of_file(in_held_out_path, in_word_freq_path);
+
+    let evaluation = lm_evaluate::evaluate(&language_model, &sentences);
+
+    let out_eval_path = in_held_out_path.to_string() + ".eval.tsv";
+    let _res = evaluation.write(&out_eval_path);
+
+    println!("\nSentences: {}  words: {}  OOV: {}", evaluation.num_sentences, evaluation.num_words, evaluation.num_oov);
+    println!("   log10 prob: {:.4}", evaluation.log_prob);
+    println!("   perplexity: {:.4}", evaluation.perplexity());
+    println!("   OOV rate:   {:.4}", evaluation.oov_rate());
+    println!("\nFile written: {}", &out_eval_path);
+}
+
+// The sentences of the corpus with the words corrected as in the 2 grams
+// generation, the words that can't be corrected are <unk>.
+fn read_corrected_sentences_of_file(in_text_corpus_path: &str, in_word_freq_path: &str) -> Vec<Vec<String>> {
+    let mut word_corrector = WordCorrector::new(get_spell_checker(), get_ortho_rules(), get_word_freq(in_word_freq_path), get_overrides());
+
+    let input_string = fs::read_to_string(in_text_corpus_path)
+        .expect("Something went wrong reading the file");
+    let input_string_corrected = input_string.nfkc().collect::<String>();
+
+    let reg_ex_pt = LangRegEx::new(Lang::PT).reg_ex_comp_word_pattern;
+
+    let mut sentences: Vec<Vec<String>> = Vec::new();
+    for phrase in input_string_corrected.split('.') {
+        let mut sentence: Vec<String> = Vec::new();
+        for word in phrase.split_whitespace() {
+            let word_only = match reg_ex_pt.find(word) {
+                Some(word_only) => word_only,
+                None => continue,
+            };
+            let lower_case_word = casing::case_fold(word_only.as_str());
+            let new_word = match word_corrector.correct(&lower_case_word) {
+                Correction::Valid => lower_case_word,
+                Correction::Overridden(rule_match) => rule_match.replacement.clone(),
+                Correction::Corrected(ranked, _) => ranked.best.replacement.clone(),
+                Correction::Rejected | Correction::NotFound(_) => ngram_lm::UNKNOWN_WORD.to_string(),
+            };
+            sentence.push(new_word);
+        }
+        if !sentence.is_empty() {
+            sentences.push(sentence);
+        }
+    }
+    sentences
+}
+
+// Counts the n grams of the corpus and writes the language model.
+fn write_arpa_of_file(in_text_corpus_path: &str, out_arpa_path: &str, order: usize, smoothing: Smoothing, in_word_freq_path: &str) {
+    let sentences = read_corrected_sentences_of_file(in_text_corpus_path, in_word_freq_path);
+
+    let mut n_gram_counts = NGramCounts::new(order);
+    for sentence in &sentences {
+        n_gram_counts.add_sentence(sentence);
+    }
+    let language_model = LanguageModel::build(&n_gram_counts, smoothing);
+    match language_model.write_arpa(out_arpa_path) {
+        Ok(()) => println!("\nFile written: {}", out_arpa_path),
+        Err(err) => println!("\nError writing the language model: {}", err),
+    }
+    for (k, n_grams) in language_model.n_grams.iter().enumerate() {
+        println!("   {}-grams: {}", k + 1, n_grams.len());
+    }
+}
+
+// Exports the words frequency and the 2 grams files to the SymSpell formats
+// and verifies that they load back.
+fn export_symspell_files(in_word_freq_path: &str, in_2_grams_path: &str, out_word_freq_path: &str, out_2_grams_path: &str) {
+    let files = [(in_word_freq_path, out_word_freq_path, 1), (in_2_grams_path, out_2_grams_path, 2)];
+    for (in_path, out_path, num_terms) in files {
+        let dic_freq = freq_dic::load_freq_file(in_path)
+            .expect("Something went wrong reading the frequency file");
+        match symspell_export::export_symspell_file(&dic_freq, num_terms, out_path) {
+            Ok(stats) => {
+                println!("\nFile written and verified: {}", out_path);
+                println!("   entries: {}  skipped: {}", stats.num_written, stats.num_skipped);
+            },
+            Err(err) => println!("\nError exporting to SymSpell: {}", err),
+        }
+    }
+}
+
+// Prints the SymSpell suggestions of one word, or the correction of a text
+// with many words.
+fn symspell_lookup(input: &str, in_word_freq_path: &str, in_2_grams_path: &str, verbosity: Verbosity) {
+    let symspell = SymSpell::from_files(in_word_freq_path, Some(in_2_grams_path), symspell::DEFAULT_MAX_EDIT_DISTANCE)
+        .expect("Something went wrong reading the frequency files");
+
+    if input.split_whitespace().count() > 1 {
+        let item = symspell.lookup_compound(input, symspell::DEFAULT_MAX_EDIT_DISTANCE);
+        println!("\n   {}  (distance: {}, count: {})", item.term, item.distance, item.count);
+    } else {
+        println!("\nThe suggestions to the word: \"{}\":", input);
+        for item in symspell.lookup(input, verbosity, symspell::DEFAULT_MAX_EDIT_DISTANCE) {
+            println!("   {}  (distance: {}, count: {})", item.term, item.distance, item.count);
+        }
+    }
+}
+
+// Restores the case of a lower case text file, writes to the out file or to
+// the console.
+fn truecase_text_of_file(in_text_path: &str, out_text_path: Option<&str>, in_truecase_path: &str) {
+    let truecaser = Truecaser::from_file(in_truecase_path)
+        .expect("Something went wrong reading the truecase file");
+
+    let input_string = fs::read_to_string(in_text_path)
+        .expect("Something went wrong reading the file");
+    let input_string_corrected = input_string.nfc().collect::<String>();
+
+    let output_string = truecaser.truecase_text(&input_string_corrected);
+
+    match out_text_path {
+        Some(path) => {
+            let _res = fs::write(path, &output_string);
+            println!("\nFile written: {}", path);
+        },
+        None => println!("\n{}", output_string),
+    }
+}
+
+// Converts a text file to the new orthographic treaty, writes the text and
+// the audit of the corrections.
+fn convert_text_of_file(in_text_path: &str, out_text_path: &str, in_word_freq_path: &str) {
+    let spell_checker = get_spell_checker();
+
+    let ortho_rules = get_ortho_rules();
+
+    let word_freq = get_word_freq(in_word_freq_path);
+
+    let overrides = get_overrides();
+
+    // HunSpell cache is inside.
+    let mut word_corrector = WordCorrector::new(spell_checker, ortho_rules, word_freq, overrides);
+
+    let mut correction_audit = CorrectionAudit::new();
+
+    // NFC and not NFKC, to compose the accents without changing the punctuation.
+    let input_string = fs::read_to_string(in_text_path)
+        .expect("Something went wrong reading the file");
+    let input_string_corrected = input_string.nfc().collect::<String>();
+
+    let reg_ex_pt = LangRegEx::new(Lang::PT).reg_ex_comp_word_pattern;
+
+    let (output_string, stats) = text_convert::convert_text(&input_string_corrected, &reg_ex_pt, &mut word_corrector, &mut correction_audit);
+
+    // Save to file.
+    let _res = fs::write(out_text_path, &output_string);
+
+    // Save to file.
+    let audit_out_text_path = out_text_path.to_string() + ".audit.tsv";
+    let _res = correction_audit.write(&audit_out_text_path);
+
+    println!("\nWords: {}  changed: {}", stats.num_words, stats.num_changed);
+    println!("\n1. With text:\n{}", out_text_path);
+    println!("\n2. With text:\n{}", &audit_out_text_path);
+}
+
+// Restores the diacritics of a text file, writes to the out file or to the
+// console.
+fn restore_diacritics_of_file(in_text_path: &str, out_text_path: Option<&str>, in_word_freq_path: &str, in_2_grams_path: &str) {
+    let restorer = DiacriticRestorer::from_files(in_word_freq_path, in_2_grams_path, get_accent_confusion())
+        .expect("Something went wrong reading the frequency files");
+
+    let input_string = fs::read_to_string(in_text_path)
+        .expect("Something went wrong reading the file");
+    let input_string_corrected = input_string.nfkc().collect::<String>();
+
+    let output_string = restorer.restore_text(&input_string_corrected);
+
+    match out_text_path {
+        Some(path) => {
+            let _res = fs::write(path, &output_string);
+            println!("\nFile written: {}", path);
+        },
+        None => println!("\n{}", output_string),
+    }
+}
+
+#[cfg(feature = "hunspell")]
+fn get_spell_checker() -> Box<dyn SpellChecker> {
     let path = "./hunspell-pt_PT-20211125/";
     let dic = "pt_PT";
 
-    Hunspell::new(&(String::new() + path + dic + ".aff"),
-                  &(String::new() + path + dic + ".dic"))
+    Box::new(Hunspell::new(&(String::new() + path + dic + ".aff"),
+                           &(String::new() + path + dic + ".dic")))
+    }
+
+// Without the HunSpell C library the words are checked with the native
+// dictionary, it has no suggestions for the rules to choose from.
+#[cfg(not(feature = "hunspell"))]
+fn get_spell_checker() -> Box<dyn SpellChecker> {
+    Box::new(get_native_dictionary())
+}
+
+// The same dictionary of HunSpell, read and expanded in Rust.
+fn get_native_dictionary() -> NativeDictionary {
+    let path = "./hunspell-pt_PT-20211125/";
+    let dic = "pt_PT";
+
+    NativeDictionary::from_files(&(String::new() + path + dic + ".aff"),
+                                 &(String::new() + path + dic + ".dic"))
+        .expect("Something went wrong reading the HunSpell dictionary")
+}
+
+// Loads the AO90 rules file, if it can't be read uses the default rules.
+fn get_ortho_rules() -> OrthoRuleSet {
+    let path = "./data/ao90_rules.txt";
+
+    match OrthoRuleSet::from_file(path) {
+        Ok(ortho_rules) => ortho_rules,
+        Err(err) => {
+            println!("\nError reading the AO90 rules, using the default rules: {}", err);
+            OrthoRuleSet::default_rules()
+        }
+    }
+}
+
+// Loads the diacritic confusion model, if it can't be read uses the default.
+fn get_accent_confusion() -> AccentConfusion {
+    let path = "./data/accent_confusion.txt";
+
+    match AccentConfusion::from_file(path) {
+        Ok(accent_confusion) => accent_confusion,
+        Err(err) => {
+            println!("\nError reading the accent confusion, using the default: {}", err);
+            AccentConfusion::default_confusion()
+        }
+    }
+}
+
+fn get_override_files() -> OverrideFiles {
+    let path = "./data/";
+
+    OverrideFiles {
+        map_path:    String::new() + path + "overrides_map.txt",
+        accept_path: String::new() + path + "overrides_accept.txt",
+        reject_path: String::new() + path + "overrides_reject.txt",
+    }
+}
+
+// Loads the manual overrides, the forced mappings and the accept and reject
+// lists. If they can't be read there are no overrides.
+fn get_overrides() -> Overrides {
+    let files = get_override_files();
+
+    // A bad line stops the run, without it all the overrides would be lost.
+    Overrides::from_files(&files.map_path, &files.accept_path, &files.reject_path)
+        .expect("Something went wrong reading the overrides")
+}
+
+// Loads the words frequency of a previous run, used to rank the corrections.
+// If it can't be read the ranking uses only the rules.
+fn get_word_freq(in_word_freq_path: &str) -> HashMap<String, u64> {
+    match freq_dic::load_freq_file(in_word_freq_path) {
+        Ok(word_freq) => word_freq,
+        Err(err) => {
+            println!("\nError reading the words frequency, ranking without it: {}", err);
+            HashMap::new()
+        }
     }
+}
 
+#[cfg(feature = "hunspell")]
 fn test_hunspell() {
     let path = "./hunspell-pt_PT-20211125/";
     let dic = "pt_PT";
@@ -195,13 +868,27 @@ fn read_all_words_freq_of_file_auto() {
     let in_filename = "small_europarl-v7.pt-en.pt";
     let in_text_corpus_path = in_path.to_string() + in_filename; 
     
+    let in_word_freq_path = r"./data/dic_corpus_unique_small.words";
+
     let out_path = r"/dev/shm/";
     let out_filename =  "dic_corpus_unique_small.words";
     let out_dic_unique_words_path = out_path.to_string() + out_filename; 
 
     let flag_check_spell = true;
 
-    read_all_words_freq_of_file(&in_text_corpus_path, &out_dic_unique_words_path, flag_check_spell); 
+    // Lemma frequencies, with Some(LemmaMode::Split) or Some(LemmaMode::Flag).
+    let lemma_mode: Option<LemmaMode> = None;
+
+    // SymSpell over the words frequency file instead of the HunSpell suggest().
+    let flag_symspell = false;
+
+    // Counts in memory, or CountingMode::Spill { memory_budget: ngram_counter::DEFAULT_MEMORY_BUDGET }
+    // for the corpora where the counts don't fit in RAM, or only the top k with
+    // CountingMode::Approximate { num_top: approx_counter::DEFAULT_NUM_TOP,
+    //     epsilon: approx_counter::DEFAULT_EPSILON, delta: approx_counter::DEFAULT_DELTA }.
+    let counting_mode = CountingMode::InMemory;
+
+    read_all_words_freq_of_file(&in_text_corpus_path, in_word_freq_path, &out_dic_unique_words_path, flag_check_spell, lemma_mode, flag_symspell, counting_mode); 
 }
 
 // Read all unique words frequency of 320 MB text big file. (20 minutes)
@@ -210,15 +897,32 @@ fn read_all_words_freq_of_file_auto_full_to_dev_shm() {
     let in_filename = "europarl-v7.pt-en.pt";
     let in_text_corpus_path = in_path.to_string() + in_filename; 
     
+    // Words frequency of a previous run.
+    let in_word_freq_path = r"/dev/shm/dic_corpus_unique.words";
+
     let out_path = r"/dev/shm/";
     let out_filename =  "dic_corpus_unique.words";
     let out_dic_unique_words_path = out_path.to_string() + out_filename; 
 
     let flag_check_spell = true;
 
-    read_all_words_freq_of_file(&in_text_corpus_path, &out_dic_unique_words_path, flag_check_spell); 
+    // Lemma frequencies, with Some(LemmaMode::Split) or Some(LemmaMode::Flag).
+    let lemma_mode: Option<LemmaMode> = None;
+
+    // SymSpell over the words frequency file instead of the HunSpell suggest().
+    let flag_symspell = false;
+
+    // Counts in memory, or CountingMode::Spill { memory_budget: ngram_counter::DEFAULT_MEMORY_BUDGET }
+    // for the corpora where the counts don't fit in RAM, or only the top k with
+    // CountingMode::Approximate { num_top: approx_counter::DEFAULT_NUM_TOP,
+    //     epsilon: approx_counter::DEFAULT_EPSILON, delta: approx_counter::DEFAULT_DELTA }.
+    let counting_mode = CountingMode::InMemory;
+
+    read_all_words_freq_of_file(&in_text_corpus_path, in_word_freq_path, &out_dic_unique_words_path, flag_check_spell, lemma_mode, flag_symspell, counting_mode); 
 }
 
+// The old one to one accents table, used by the default rules. The rules
+// file uses the many to many model of accent_confusion.rs.
 fn map_accents() -> HashMap<char, char> {
     let map_accents: HashMap<char, char> = HashMap::from([
                         ('ê','e'),
@@ -246,71 +950,22 @@ fn map_accents() -> HashMap<char, char> {
 // It also tests the world for upper case of the first word in case of
 // a name, a country or a short form of writing (in this last case all
 // letters are uppercase).
-fn from_old_treaty_to_new_treaty_orthographic(word: &str, suggestion_vec: &Vec<String>, map_accents_p: &HashMap<char, char>) -> Option<String> {
-
-    fn test_string(mod_word: &str, suggestion_vec: &Vec<String>) -> Option<String> {
-        for sug in suggestion_vec {
-            if *sug == mod_word {
-                return Some(mod_word.to_string());
-            }
-        }
-        None    
-    }
-
-    let mut tmp_string = String::with_capacity(50);
-
-    // First letter upper case.
-    // Ex: alemanha vs Alemanha
-    let mut chars_vec  = word.get_vec_chars();
-    let up_vec: Vec<_> = chars_vec[0].to_uppercase().collect();
-    chars_vec[0] = up_vec[0];
-    chars_vec.to_string_buf(&mut tmp_string);
-    if let Some(found) = test_string(&tmp_string, suggestion_vec) {
-        return Some(found);
-    }
-
-    // All letter upper case.
-    // Ex: opec vs OPEC
-    let all_letter_upper_case = word.to_uppercase();
-    if let Some(found) = test_string(&all_letter_upper_case, suggestion_vec) {
-        return Some(found);
-    }
-
-    // The suggestion has one more 'c' or one more 'p' then take the suggestion word.
-    // Ex: acta    vs ata 
-    //     adopção vs adoção.
-    let mut chars_vec  = word.get_vec_chars();
-    for i in 1..(chars_vec.len() - 1) {
-        // Remove 'c' or 'p'
-        let mut ch: Option<char> = None;
-        if chars_vec[i] == 'c' || chars_vec[i] == 'p' {
-            ch = Some(chars_vec.remove(i));
-        } 
-        chars_vec.to_string_buf(&mut tmp_string);
-        if let Some(found) = test_string(&tmp_string, suggestion_vec) {
-            return Some(found);
-        }
-        if let Some(ch_tmp) = ch {
-            chars_vec.insert(i, ch_tmp);
-        }
-    }
+// The candidates of all the rules in the rules file are ranked by the type
+// of edit, the priority of the rule and the frequency of the word in the
+// corpus, see ortho_rules.rs. The best candidate says which rule fired.
+fn from_old_treaty_to_new_treaty_orthographic(word: &str, suggestion_vec: &Vec<String>, ortho_rules: &OrthoRuleSet, word_freq: &HashMap<String, u64>,
+                                               overrides: &Overrides) -> Option<RankedCorrection> {
+    ortho_rules.rank(word, suggestion_vec, word_freq, &|replacement| overrides.is_rejected(replacement))
+}
 
-    // Test every combination of distance one accents.
-    let mut chars_vec  = word.get_vec_chars();
-    for i in 0..chars_vec.len() {
-        let new_char = map_accents_p.get(&chars_vec[i]);
-        if let Some(new_char_tmp) = new_char {
-            let old_char = chars_vec[i];
-            chars_vec[i] = *new_char_tmp;
-            chars_vec.to_string_buf(&mut tmp_string);
-            if let Some(found) = test_string(&tmp_string, suggestion_vec) {
-                return Some(found);
-            }
-            chars_vec[i] = old_char;
-        }
+// Key of the ties file, "word -> best tie_1 tie_2".
+fn make_ties_key(word: &str, ranked: &RankedCorrection) -> String {
+    let mut key = word.to_string() + " -> " + &ranked.best.replacement;
+    for tie in &ranked.ties {
+        key.push(' ');
+        key.push_str(&tie.replacement);
     }
-
-    None
+    key
 }
 
 fn make_string_from_BTreeHap(dic_word_freq: &BTreeMap<String, u64>) -> String {
@@ -324,10 +979,37 @@ fn make_string_from_BTreeHap(dic_word_freq: &BTreeMap<String, u64>) -> String {
     dic_unique_freq_words_string
 }
 
-fn read_all_words_freq_of_file(in_text_corpus_path: &str, out_dic_unique_words_path: &str, flag_check_spell: bool) {
-    let hunspell = get_hunspell();
+// The runs merged by the Spill counting, and the memory of the vocabulary
+// that isn't in the budget of the Spill and Approximate counting.
+fn print_counting_report<const N: usize>(counter: &dyn NGramCounter<N>, vocabulary: &Vocabulary, counting_mode: CountingMode) {
+    match counting_mode {
+        CountingMode::InMemory => return,
+        CountingMode::Spill { memory_budget } => {
+            println!("\nMerged {} runs of counts, memory budget: {} bytes.", counter.num_runs(), memory_budget);
+        },
+        CountingMode::Approximate { .. } => (),
+    }
+    println!("\nVocabulary: {} words  {} bytes, not in the memory of the counts.", vocabulary.len(), vocabulary.num_bytes());
+}
+
+fn read_all_words_freq_of_file(in_text_corpus_path: &str, in_word_freq_path: &str, out_dic_unique_words_path: &str, flag_check_spell: bool, lemma_mode: Option<LemmaMode>, flag_symspell: bool, counting_mode: CountingMode) {
+    let spell_checker = get_spell_checker();
     
-    let map_accents_tmp = map_accents();
+    let ortho_rules = get_ortho_rules();
+
+    let word_freq = get_word_freq(in_word_freq_path);
+
+    let overrides = get_overrides();
+
+    let symspell = if flag_symspell {
+        Some(SymSpell::from_word_freq(&word_freq, symspell::DEFAULT_MAX_EDIT_DISTANCE))
+    } else {
+        None
+    };
+
+    // HunSpell cache is inside.
+    let mut word_corrector = WordCorrector::new(spell_checker, ortho_rules, word_freq, overrides);
+    word_corrector.symspell = symspell;
 
     use std::env;
     
@@ -344,17 +1026,25 @@ fn read_all_words_freq_of_file(in_text_corpus_path: &str, out_dic_unique_words_p
 
     let reg_ex_pt = LangRegEx::new(Lang::PT).reg_ex_comp_word_pattern;
 
-    // HunSpell cache
-    let mut hunspell_word_cache: HashMap<String, String> = HashMap::new(); 
-
-    // Counts all words.
-    let mut dic_unique_freq_words: BTreeMap<String, u64> = BTreeMap::new();
+    // Counts all words, by the ids of the vocabulary.
+    let mut vocabulary = Vocabulary::new();
+    let mut dic_unique_freq_ids = ngram_counter::make_counter::<1>(counting_mode);
 
     let mut not_dic_unique_freq_words: BTreeMap<String, u64> = BTreeMap::new();
 
     let mut dic_not_check_unique_freq_words: BTreeMap<String, u64> = BTreeMap::new();
 
+    // Corrections where more then one candidate had the best score.
+    let mut dic_ties_unique_freq_words: BTreeMap<String, u64> = BTreeMap::new();
+
+    // Which rule changed which word, and how often.
+    let mut correction_audit = CorrectionAudit::new();
+
+    // Case variants of each word, without the first word of the sentences.
+    let mut truecase_counter = TruecaseCounter::new();
+
     for phrase in input_string_corrected.split('.') {
+        let mut flag_sentence_start = true;
         for word in phrase.split_whitespace() {
             let captures = reg_ex_pt.captures(word);
             if captures.is_none() {
@@ -364,55 +1054,70 @@ fn read_all_words_freq_of_file(in_text_corpus_path: &str, out_dic_unique_words_p
             }
             let captures = captures.unwrap();
             if let Some(word_only) = captures.get(0) {
-                let mut lower_case_word = word_only.as_str().to_ascii_lowercase();
-
-                let flag_word_exists = hunspell.check(&lower_case_word);
-                if !flag_word_exists {
-                    // See if the correct map of the word to the hunspell dictionary is on the cache, if it is use it.
-                    let correct_word = hunspell_word_cache.get(&lower_case_word);
-                    let res;
-                    if correct_word.is_none() {
-                        let suggestion_vec = hunspell.suggest(&lower_case_word);
-                        res = from_old_treaty_to_new_treaty_orthographic(&lower_case_word, &suggestion_vec, &map_accents_tmp);
-                        if res.is_some() {
-                            // Substituted the word for a correct word.
-                            let new_word = res.unwrap();
-                            hunspell_word_cache.insert(lower_case_word.clone(), new_word.clone());
-                            // Let's use it!
-                            lower_case_word = new_word;   
-                        } else {
-                            // Didn't found a correct substitution, so puts the word in the not check_dic. 
-                            hunspell_word_cache.insert(lower_case_word.clone(), "*".to_string() + &suggestion_vec.join(" "));
-                            let count = dic_not_check_unique_freq_words.entry(lower_case_word + " -> " + &suggestion_vec.join(" ")).or_insert(0);
+                let mut cased_word = CasedWord::new(word_only.as_str(), flag_sentence_start);
+                flag_sentence_start = false;
+                let mut lower_case_word = cased_word.key.clone();
+
+                // Ex: Janeiro -> janeiro, the key is already lower case, the
+                // rule changes the case kept in the truecase table.
+                if let Some(rule_match) = word_corrector.lower_case_rule(word_only.as_str(), cased_word.flag_sentence_start) {
+                    correction_audit.record_rule_match(word_only.as_str(), &rule_match);
+                    cased_word.pattern = CasePattern::Lower;
+                }
+
+                let correction = word_corrector.correct(&lower_case_word);
+                correction_audit.record(&lower_case_word, correction);
+                match correction {
+                    Correction::Valid => (),
+                    Correction::Overridden(rule_match) => {
+                        lower_case_word = rule_match.replacement.clone();
+                    },
+                    Correction::Rejected => continue,
+                    Correction::Corrected(ranked, _) => {
+                        // Substituted the word for a correct word.
+                        if !ranked.ties.is_empty() {
+                            let count = dic_ties_unique_freq_words.entry(make_ties_key(&lower_case_word, ranked)).or_insert(0);
                             *count += 1;
-                            continue;    
                         }
-                    } else {
-                        // Found the word in hunspell cache.
-                        let inner_correct_word = correct_word.unwrap();
-                        if inner_correct_word.starts_with("*") {
-                            // The word is invalid, because it could find a correct word for it. 
-                            continue;
-                        }
-                        // The word that was on hunspell cache is a correct word.
                         // Let's use it!
-                        lower_case_word = inner_correct_word.clone();
-                    }
+                        lower_case_word = ranked.best.replacement.clone();
+                    },
+                    Correction::NotFound(suggestion_vec) => {
+                        // Didn't found a correct substitution, so puts the word in the not check_dic. 
+                        let count = dic_not_check_unique_freq_words.entry(review_tui::make_not_check_key(&lower_case_word, &suggestion_vec)).or_insert(0);
+                        *count += 1;
+                        continue;
+                    },
                 }
-                let count = dic_unique_freq_words.entry(lower_case_word).or_insert(0);
-                *count += 1;
+                if !cased_word.flag_sentence_start {
+                    truecase_counter.add(&cased_word.restore(&lower_case_word));
+                }
+                let word_id = vocabulary.intern(&lower_case_word);
+                dic_unique_freq_ids.count(&vocabulary, [word_id])
+                    .expect("Something went wrong writing the counts");
             }
         }
     }
 
-    let dic_unique_freq_words_string = make_string_from_BTreeHap(&dic_unique_freq_words);
-
     let not_dic_unique_freq_words_string = make_string_from_BTreeHap(&not_dic_unique_freq_words);
 
     let dic_not_check_unique_freq_words_string = make_string_from_BTreeHap(&dic_not_check_unique_freq_words);
 
+    let dic_ties_unique_freq_words_string = make_string_from_BTreeHap(&dic_ties_unique_freq_words);
+
     // Save to file.
-    let _res = fs::write(&out_dic_unique_words_path, &dic_unique_freq_words_string);
+    let num_unique_words = ngram_counter::write_counts_file(dic_unique_freq_ids.as_mut(), &vocabulary, out_dic_unique_words_path)
+        .expect("Something went wrong writing the file");
+    println!("\nUnique words: {}", num_unique_words);
+    print_counting_report(dic_unique_freq_ids.as_ref(), &vocabulary, counting_mode);
+
+    // Save to file.
+    let bounds_out_dic_unique_words_path = out_dic_unique_words_path.replace("dic", "approx_bounds_dic");
+    if let Some(bounds_string) = dic_unique_freq_ids.make_bounds_string(&vocabulary) {
+        let _res = fs::write(&bounds_out_dic_unique_words_path, bounds_string);
+        println!("\n{}", dic_unique_freq_ids.error_summary().unwrap_or_default());
+        println!("\n2. With text:\n{}", &bounds_out_dic_unique_words_path);
+    }
 
     // println!("\nFile written: {}", &dic_unique_freq_words_string);
 
@@ -424,14 +1129,50 @@ fn read_all_words_freq_of_file(in_text_corpus_path: &str, out_dic_unique_words_p
     let not_out_dic_not_check_unique_words_path = out_dic_unique_words_path.replace("dic", "not_check_dic");
     let _res = fs::write(&not_out_dic_not_check_unique_words_path, &dic_not_check_unique_freq_words_string);
 
+    // Save to file.
+    let ties_out_dic_unique_words_path = out_dic_unique_words_path.replace("dic", "ties_dic");
+    let _res = fs::write(&ties_out_dic_unique_words_path, &dic_ties_unique_freq_words_string);
+
+    // Save to file.
+    let audit_out_dic_unique_words_path = out_dic_unique_words_path.replace("dic", "audit_dic");
+    let _res = correction_audit.write(&audit_out_dic_unique_words_path);
+
+    // Save to file.
+    let truecase_out_dic_unique_words_path = out_dic_unique_words_path.replace("dic", "truecase_dic");
+    let _res = fs::write(&truecase_out_dic_unique_words_path, truecase_counter.make_string());
+
+    // Save to file.
+    let lemma_out_dic_unique_words_path = out_dic_unique_words_path.replace("dic", "lemma_dic");
+    let ambiguous_out_dic_unique_words_path = out_dic_unique_words_path.replace("dic", "lemma_ambiguous_dic");
+    if let Some(mode) = lemma_mode {
+        // The counts written, all in memory, it isn't the Spill counting.
+        let dic_unique_freq_words = freq_dic::load_freq_file(out_dic_unique_words_path)
+            .expect("Something went wrong reading the file");
+        let mut lemma_counter = LemmaCounter::new(mode);
+        for (word, count) in &dic_unique_freq_words {
+            lemma_counter.add_word(word_corrector.spell_checker.as_ref(), word, *count);
+        }
+        let _res = fs::write(&lemma_out_dic_unique_words_path, make_string_from_BTreeHap(&lemma_counter.lemma_freq));
+        let _res = fs::write(&ambiguous_out_dic_unique_words_path, make_string_from_BTreeHap(&lemma_counter.ambiguous_freq));
+        println!("\nLemmas: {}  ambiguous words: {}", lemma_counter.lemma_freq.len(), lemma_counter.ambiguous_freq.len());
+    }
+
     // println!("\nhunspell_word_cache: \n {:?}", hunspell_word_cache);
-    println!("\nhunspell_word_cache.len(): \n {}", hunspell_word_cache.len());
+    println!("\nhunspell_word_cache.len(): \n {}", word_corrector.hunspell_word_cache.len());
 
     // println!("\nWords not written: {}", &not_dic_unique_freq_words_string);
 
     println!("\n1. With text:\n{}", &out_dic_unique_words_path);
     println!("\n2. With text:\n{}", &not_out_dic_unique_words_path);
     println!("\n2. With text:\n{}", &not_out_dic_not_check_unique_words_path);
+    println!("\n3. With text:\n{}", &ties_out_dic_unique_words_path);
+    println!("\n4. With text:\n{}", &audit_out_dic_unique_words_path);
+    println!("\n5. With text:\n{}", &truecase_out_dic_unique_words_path);
+    if lemma_mode.is_some() {
+        println!("\n6. With text:\n{}", &lemma_out_dic_unique_words_path);
+        println!("\n6. With text:\n{}", &ambiguous_out_dic_unique_words_path);
+    }
+    println!("\nCorrections by rule:\n {:?}", correction_audit.count_by_rule());
 }
 
 // Generate 2 grams for small file (seconds).
@@ -444,9 +1185,29 @@ fn read_all_2_grams_words_of_file_auto() {
     let out_filename =  "2_grams_small.words";
     let out_2_grams_words_path = out_path.to_string() + out_filename; 
 
+    let in_word_freq_path = r"./data/dic_corpus_unique_small.words";
+
     let flag_check_words = true;
 
-    read_all_2_grams_words_of_file(&in_text_corpus_path, &out_2_grams_words_path, flag_check_words);
+    // Re-scores the ambiguous words with the 2 grams of a first pass.
+    let flag_context_pass = false;
+
+    // Lemma 2 grams, with Some(LemmaMode::Split) or Some(LemmaMode::Flag).
+    let lemma_mode: Option<LemmaMode> = None;
+
+    // Part of speech 1, 2 and 3 grams.
+    let flag_pos_n_grams = false;
+
+    // SymSpell over the words frequency file instead of the HunSpell suggest().
+    let flag_symspell = false;
+
+    // Counts in memory, or CountingMode::Spill { memory_budget: ngram_counter::DEFAULT_MEMORY_BUDGET }
+    // for the corpora where the counts don't fit in RAM, or only the top k with
+    // CountingMode::Approximate { num_top: approx_counter::DEFAULT_NUM_TOP,
+    //     epsilon: approx_counter::DEFAULT_EPSILON, delta: approx_counter::DEFAULT_DELTA }.
+    let counting_mode = CountingMode::InMemory;
+
+    read_all_2_grams_words_of_file(&in_text_corpus_path, in_word_freq_path, &out_2_grams_words_path, flag_check_words, flag_context_pass, lemma_mode, flag_pos_n_grams, flag_symspell, counting_mode);
 }
 
 // Generate 2 grams for big file 320 MB (20 minutes).
@@ -459,15 +1220,49 @@ fn read_all_2_grams_words_of_file_auto_full_to_dev_shm() {
     let out_filename =  "2_grams_big.words";
     let out_2_grams_words_path = out_path.to_string() + out_filename; 
 
+    let in_word_freq_path = r"/dev/shm/dic_corpus_unique.words";
+
     let flag_check_words = true;
 
-    read_all_2_grams_words_of_file(&in_text_corpus_path, &out_2_grams_words_path, flag_check_words);
+    // Re-scores the ambiguous words with the 2 grams of a first pass.
+    let flag_context_pass = false;
+
+    // Lemma 2 grams, with Some(LemmaMode::Split) or Some(LemmaMode::Flag).
+    let lemma_mode: Option<LemmaMode> = None;
+
+    // Part of speech 1, 2 and 3 grams.
+    let flag_pos_n_grams = false;
+
+    // SymSpell over the words frequency file instead of the HunSpell suggest().
+    let flag_symspell = false;
+
+    // Counts in memory, or CountingMode::Spill { memory_budget: ngram_counter::DEFAULT_MEMORY_BUDGET }
+    // for the corpora where the counts don't fit in RAM, or only the top k with
+    // CountingMode::Approximate { num_top: approx_counter::DEFAULT_NUM_TOP,
+    //     epsilon: approx_counter::DEFAULT_EPSILON, delta: approx_counter::DEFAULT_DELTA }.
+    let counting_mode = CountingMode::InMemory;
+
+    read_all_2_grams_words_of_file(&in_text_corpus_path, in_word_freq_path, &out_2_grams_words_path, flag_check_words, flag_context_pass, lemma_mode, flag_pos_n_grams, flag_symspell, counting_mode);
 }
 
-fn read_all_2_grams_words_of_file(in_text_corpus_path: &str, out_2_grams_words_path: &str, flag_check_hunspell: bool) {
-    let hunspell = get_hunspell();
+fn read_all_2_grams_words_of_file(in_text_corpus_path: &str, in_word_freq_path: &str, out_2_grams_words_path: &str, flag_check_hunspell: bool, flag_context_pass: bool, lemma_mode: Option<LemmaMode>, flag_pos_n_grams: bool, flag_symspell: bool, counting_mode: CountingMode) {
+    let spell_checker = get_spell_checker();
+
+    let ortho_rules = get_ortho_rules();
+
+    let word_freq = get_word_freq(in_word_freq_path);
 
-    let map_accents_tmp = map_accents();
+    let overrides = get_overrides();
+
+    let symspell = if flag_symspell {
+        Some(SymSpell::from_word_freq(&word_freq, symspell::DEFAULT_MAX_EDIT_DISTANCE))
+    } else {
+        None
+    };
+
+    // HunSpell cache is inside.
+    let mut word_corrector = WordCorrector::new(spell_checker, ortho_rules, word_freq, overrides);
+    word_corrector.symspell = symspell;
 
     use std::env;
     
@@ -484,18 +1279,26 @@ fn read_all_2_grams_words_of_file(in_text_corpus_path: &str, out_2_grams_words_p
 
     let reg_ex_pt = LangRegEx::new(Lang::PT).reg_ex_comp_word_pattern;
 
-    // HunSpell cache
-    let mut hunspell_word_cache: HashMap<String, String> = HashMap::new(); 
-
-    // Counts all words.
-    let mut dic_2_grams_freq_words: BTreeMap<String, u64> = BTreeMap::new();
+    // Counts all words, the 2 grams by the ids of the vocabulary.
+    let mut vocabulary = Vocabulary::new();
+    let mut dic_2_grams_freq_ids = ngram_counter::make_counter::<2>(counting_mode);
 
     let mut not_dic_2_grams_freq_words: BTreeMap<String, u64> = BTreeMap::new();
 
     let mut not_dic_2_grams_not_check_freq_words: BTreeMap<String, u64> = BTreeMap::new();
 
+    // Corrections where more then one candidate had the best score.
+    let mut ties_2_grams_freq_words: BTreeMap<String, u64> = BTreeMap::new();
+
+    // Which rule changed which word, and how often.
+    let mut correction_audit = CorrectionAudit::new();
+
+    // Part of speech n grams of the corrected words.
+    let mut pos_counter = PosNGramCounter::new();
+
     for phrase in input_string_corrected.split('.') {
-        let mut prev_word: Option<String> = None;
+        pos_counter.end_sentence();
+        let mut prev_word: Option<u32> = None;
         for word in phrase.split_whitespace() {
             let captures = reg_ex_pt.captures(word);
             if captures.is_none() {
@@ -505,67 +1308,102 @@ fn read_all_2_grams_words_of_file(in_text_corpus_path: &str, out_2_grams_words_p
             }
             let captures = captures.unwrap();
             if let Some(word_only) = captures.get(0) {
-                let mut lower_case_word = word_only.as_str().to_ascii_lowercase();
+                let mut lower_case_word = casing::case_fold(word_only.as_str());
 
                 /* Start */
-                let flag_word_exists = hunspell.check(&lower_case_word);
-                if !flag_word_exists {
-                    // See if the correct map of the word to the hunspell dictionary is on the cache, if it is use it.
-                    let correct_word = hunspell_word_cache.get(&lower_case_word);
-                    let res;
-                    if correct_word.is_none() {
-                        let suggestion_vec = hunspell.suggest(&lower_case_word);
-                        res = from_old_treaty_to_new_treaty_orthographic(&lower_case_word, &suggestion_vec, &map_accents_tmp);
-                        if res.is_some() {
-                            // Substituted the word for a correct word.
-                            let new_word = res.unwrap();
-                            hunspell_word_cache.insert(lower_case_word.clone(), new_word.clone());
-                            // Let's use it!
-                            lower_case_word = new_word;   
-                        } else {
-                            // Didn't found a correct substitution, so puts the word in the not check_dic.
-                            hunspell_word_cache.insert(lower_case_word.clone(), "*".to_string() + &suggestion_vec.join(" "));
-                            let count = not_dic_2_grams_not_check_freq_words.entry(lower_case_word + " -> " + &suggestion_vec.join(" ")).or_insert(0);
+                let correction = word_corrector.correct(&lower_case_word);
+                correction_audit.record(&lower_case_word, correction);
+                match correction {
+                    Correction::Valid => (),
+                    Correction::Overridden(rule_match) => {
+                        lower_case_word = rule_match.replacement.clone();
+                    },
+                    Correction::Rejected => {
+                        prev_word = None;
+                        pos_counter.end_sentence();
+                        continue;
+                    },
+                    Correction::Corrected(ranked, _) => {
+                        // Substituted the word for a correct word.
+                        if !ranked.ties.is_empty() {
+                            let count = ties_2_grams_freq_words.entry(make_ties_key(&lower_case_word, ranked)).or_insert(0);
                             *count += 1;
-                            prev_word = None;  // diff
-                            continue;    
-                        }
-                    } else {
-                        // Found the word in hunspell cache.
-                        let inner_correct_word = correct_word.unwrap();
-                        if inner_correct_word.starts_with("*") {
-                            // The word is invalid, because it could find a correct word for it. 
-                            prev_word = None; // diff
-                            continue;
                         }
-                        // The word that was on hunspell cache is a correct word.
                         // Let's use it!
-                        lower_case_word = inner_correct_word.clone();
-                    }
+                        lower_case_word = ranked.best.replacement.clone();
+                    },
+                    Correction::NotFound(suggestion_vec) => {
+                        // Didn't found a correct substitution, so puts the word in the not check_dic.
+                        let count = not_dic_2_grams_not_check_freq_words.entry(review_tui::make_not_check_key(&lower_case_word, &suggestion_vec)).or_insert(0);
+                        *count += 1;
+                        prev_word = None;  // diff
+                        if flag_pos_n_grams {
+                            pos_counter.add_unknown();
+                        }
+                        continue;
+                    },
+                }
+
+                if flag_pos_n_grams {
+                    pos_counter.add_word(word_corrector.spell_checker.as_ref(), &lower_case_word);
                 }
 
-                if prev_word.is_some() {
-                    let word_2_grams = prev_word.unwrap() + " " +  &lower_case_word;
-                    
-                    let count = dic_2_grams_freq_words.entry(word_2_grams).or_insert(0);
-                    *count += 1;
+                let word_id = vocabulary.intern(&lower_case_word);
+                if let Some(prev_id) = prev_word {
+                    dic_2_grams_freq_ids.count(&vocabulary, [prev_id, word_id])
+                        .expect("Something went wrong writing the counts");
                 }
 
-                prev_word = Some(lower_case_word);
+                prev_word = Some(word_id);
 
                 /* End */
             }
         }
     }
 
-    let dic_2_grams_freq_words_string = make_string_from_BTreeHap(&dic_2_grams_freq_words);
+    // Save to file.
+    let mut num_2_grams = ngram_counter::write_counts_file(dic_2_grams_freq_ids.as_mut(), &vocabulary, out_2_grams_words_path)
+        .expect("Something went wrong writing the file");
+    println!("\nVocabulary: {} words  2 grams: {}", vocabulary.len(), num_2_grams);
+    print_counting_report(dic_2_grams_freq_ids.as_ref(), &vocabulary, counting_mode);
+
+    // Save to file.
+    let bounds_2_grams_freq_words_path = out_2_grams_words_path.replace("2_grams", "approx_bounds_2_grams");
+    if let Some(bounds_string) = dic_2_grams_freq_ids.make_bounds_string(&vocabulary) {
+        let _res = fs::write(&bounds_2_grams_freq_words_path, bounds_string);
+        println!("\n{}", dic_2_grams_freq_ids.error_summary().unwrap_or_default());
+        println!("\n3. With text:\n{}", &bounds_2_grams_freq_words_path);
+    }
+
+    // The context pass and the lemmas need all the 2 grams in memory, they
+    // are read from the file written, it isn't the Spill counting.
+    let mut dic_2_grams_freq_words: BTreeMap<String, u64> = BTreeMap::new();
+    if flag_context_pass || lemma_mode.is_some() {
+        dic_2_grams_freq_words = freq_dic::load_freq_file(out_2_grams_words_path)
+            .expect("Something went wrong reading the file")
+            .into_iter()
+            .collect();
+    }
+
+    // Second pass, chooses between the alternatives of the ambiguous words
+    // with the 2 grams counts of the first pass.
+    let mut context_2_grams_freq_words: BTreeMap<String, u64> = BTreeMap::new();
+    if flag_context_pass {
+        let (dic_2_grams_context_freq_words, context_changes) =
+            context_second_pass(&input_string_corrected, &reg_ex_pt, &mut word_corrector, &dic_2_grams_freq_words);
+        dic_2_grams_freq_words = dic_2_grams_context_freq_words;
+        context_2_grams_freq_words = context_changes;
+        num_2_grams = dic_2_grams_freq_words.len() as u64;
+
+        // Save to file.
+        let _res = fs::write(&out_2_grams_words_path, make_string_from_BTreeHap(&dic_2_grams_freq_words));
+    }
 
     let not_dic_2_grams_freq_words_string = make_string_from_BTreeHap(&not_dic_2_grams_freq_words);
 
     let not_dic_2_grams_not_check_freq_words_string = make_string_from_BTreeHap(&not_dic_2_grams_not_check_freq_words);
 
-    // Save to file.
-    let _res = fs::write(&out_2_grams_words_path, &dic_2_grams_freq_words_string);
+    let ties_2_grams_freq_words_string = make_string_from_BTreeHap(&ties_2_grams_freq_words);
 
     //println!("\nFile written: {}", &dic_unique_freq_words_string);
 
@@ -578,12 +1416,145 @@ fn read_all_2_grams_words_of_file(in_text_corpus_path: &str, out_2_grams_words_p
     let not_dic_2_grams_not_check_freq_words_path = out_2_grams_words_path.replace("2_grams", "not_2_grams_not_check_dic");
     let _res = fs::write(&not_dic_2_grams_not_check_freq_words_path, &not_dic_2_grams_not_check_freq_words_string);
 
+    // Save to file.
+    let ties_2_grams_freq_words_path = out_2_grams_words_path.replace("2_grams", "ties_2_grams");
+    let _res = fs::write(&ties_2_grams_freq_words_path, &ties_2_grams_freq_words_string);
+
+    // Save to file.
+    let audit_2_grams_freq_words_path = out_2_grams_words_path.replace("2_grams", "audit_2_grams");
+    let _res = correction_audit.write(&audit_2_grams_freq_words_path);
+
+    // Save to file.
+    let context_2_grams_freq_words_path = out_2_grams_words_path.replace("2_grams", "context_2_grams");
+    if flag_context_pass {
+        let context_2_grams_freq_words_string = make_string_from_BTreeHap(&context_2_grams_freq_words);
+        let _res = fs::write(&context_2_grams_freq_words_path, &context_2_grams_freq_words_string);
+    }
+
+    // Save to file.
+    let lemma_2_grams_freq_words_path = out_2_grams_words_path.replace("2_grams", "lemma_2_grams");
+    if let Some(mode) = lemma_mode {
+        let mut lemma_counter = LemmaCounter::new(mode);
+        for (words, count) in &dic_2_grams_freq_words {
+            if let Some((word_1, word_2)) = words.split_once(' ') {
+                lemma_counter.add_2_grams(word_corrector.spell_checker.as_ref(), word_1, word_2, *count);
+            }
+        }
+        let _res = fs::write(&lemma_2_grams_freq_words_path, make_string_from_BTreeHap(&lemma_counter.lemma_2_grams_freq));
+    }
+
+    // Save to file.
+    let pos_n_grams_paths: Vec<String> = (1..=3)
+        .map(|n| out_2_grams_words_path.replace("2_grams", &format!("pos_{}_grams", n)))
+        .collect();
+    if flag_pos_n_grams {
+        let _res = fs::write(&pos_n_grams_paths[0], make_string_from_BTreeHap(&pos_counter.pos_1_grams_freq));
+        let _res = fs::write(&pos_n_grams_paths[1], make_string_from_BTreeHap(&pos_counter.pos_2_grams_freq));
+        let _res = fs::write(&pos_n_grams_paths[2], make_string_from_BTreeHap(&pos_counter.pos_3_grams_freq));
+        println!("\nPart of speech tags unknown: {}  ambiguous: {}", pos_counter.num_unknown, pos_counter.num_ambiguous);
+    }
+
     println!("\nWords not written: {}", &not_dic_2_grams_freq_words_string);
 
     println!("\n1. With text:\n{}", &out_2_grams_words_path);
     println!("\n2. With text:\n{}", &not_out_2_grams_words_path);
+    println!("\n3. With text:\n{}", &ties_2_grams_freq_words_path);
+    println!("\n3. With text:\n{}", &audit_2_grams_freq_words_path);
+    if flag_context_pass {
+        println!("\n3. With text:\n{}", &context_2_grams_freq_words_path);
+    }
+    if lemma_mode.is_some() {
+        println!("\n3. With text:\n{}", &lemma_2_grams_freq_words_path);
+    }
+    if flag_pos_n_grams {
+        for pos_n_grams_path in &pos_n_grams_paths {
+            println!("\n3. With text:\n{}", pos_n_grams_path);
+        }
+    }
+
+    println!("\n4. dic_2_grams_freq_words.len():\n  {}", num_2_grams);
+}
+
+// The alternatives of one word for the context pass, the first one is the
+// choice of the first pass. The words that can't be corrected are None.
+fn context_alternatives(lower_case_word: &str, word_corrector: &mut WordCorrector,
+                        accent_confusion: &AccentConfusion,
+                        variants_cache: &mut HashMap<String, Vec<String>>) -> Option<Vec<String>> {
+    match word_corrector.correct(lower_case_word) {
+        Correction::Valid => (),
+        Correction::Overridden(rule_match) => return Some(vec![rule_match.replacement.clone()]),
+        Correction::Rejected => return None,
+        Correction::Corrected(ranked, _) => {
+            return Some(ranked.candidates.iter().map(|cand| cand.replacement.clone()).collect());
+        },
+        Correction::NotFound(_) => return None,
+    }
+    // The word is valid, but the accent variants can also be valid words, ex: "à" vs "a".
+    if !variants_cache.contains_key(lower_case_word) {
+        let mut alternatives = vec![lower_case_word.to_string()];
+        for (variant, _cost) in accent_confusion.variants_iter(lower_case_word) {
+            if word_corrector.spell_checker.check(&variant) {
+                alternatives.push(variant);
+            }
+        }
+        variants_cache.insert(lower_case_word.to_string(), alternatives);
+    }
+    Some(variants_cache[lower_case_word].clone())
+}
+
+// Re-counts the 2 grams choosing each ambiguous word by the 2 grams counts
+// of the first pass with the left and the right neighbors. Returns the new
+// 2 grams counts and the counts of the changes "first_pass -> chosen".
+fn context_second_pass(input_string_corrected: &str, reg_ex_pt: &Regex, word_corrector: &mut WordCorrector,
+                       dic_2_grams_freq_words: &BTreeMap<String, u64>) -> (BTreeMap<String, u64>, BTreeMap<String, u64>) {
+    // For the valid words only one accent change is tested, each variant is
+    // a call to HunSpell check().
+    let mut accent_confusion = get_accent_confusion();
+    accent_confusion.max_cost = 1;
+    let mut variants_cache: HashMap<String, Vec<String>> = HashMap::new();
+
+    let word_freq = word_corrector.word_freq.clone();
+    let context_rescorer = ContextRescorer::new(dic_2_grams_freq_words, &word_freq);
+
+    let mut vocabulary = Vocabulary::new();
+    let mut dic_2_grams_context_freq_ids: IdCounter<2> = IdCounter::new();
+    let mut context_changes: BTreeMap<String, u64> = BTreeMap::new();
+
+    for phrase in input_string_corrected.split('.') {
+        let mut phrase_alternatives: Vec<Option<Vec<String>>> = Vec::new();
+        for word in phrase.split_whitespace() {
+            if let Some(word_only) = reg_ex_pt.find(word) {
+                let lower_case_word = casing::case_fold(word_only.as_str());
+                phrase_alternatives.push(context_alternatives(&lower_case_word, word_corrector, &accent_confusion, &mut variants_cache));
+            }
+        }
+
+        let mut prev_word: Option<String> = None;
+        for i in 0..phrase_alternatives.len() {
+            let alternatives = match &phrase_alternatives[i] {
+                Some(alternatives) => alternatives,
+                None => {
+                    prev_word = None;
+                    continue;
+                },
+            };
+            let right = phrase_alternatives.get(i + 1)
+                .and_then(|next| next.as_ref())
+                .map(|next| next[0].as_str());
+            let chosen = context_rescorer.choose(prev_word.as_deref(), alternatives, right).to_string();
+            if chosen != alternatives[0] {
+                let count = context_changes.entry(alternatives[0].clone() + " -> " + &chosen).or_insert(0);
+                *count += 1;
+            }
+
+            if let Some(prev) = &prev_word {
+                dic_2_grams_context_freq_ids.add([vocabulary.intern(prev), vocabulary.intern(&chosen)]);
+            }
+            prev_word = Some(chosen);
+        }
+    }
 
-    println!("\n3. dic_2_grams_freq_words.len():\n  {}", dic_2_grams_freq_words.len());
+    (dic_2_grams_context_freq_ids.to_string_map(&vocabulary), context_changes)
 }
 
 enum Lang {
@@ -0,0 +1,76 @@
+/// Read only view of a binary file, memory mapped with memmap2, or read to
+/// memory with fs::read() if the map fails. The readers of the binary
+/// indexes (autocomplete.rs, ngram_store.rs) only see the bytes.
+///
+/// A mapped file is shared with the file system: if the file is changed
+/// while it's mapped the bytes change under the readers, and if it's
+/// truncated the reads past the new end get a SIGBUS. So open() is unsafe,
+/// the caller must make sure the file isn't changed while it's open (the
+/// index files are written once by the *_build sub commands). read() is the
+/// safe version, a copy of the file.
+///
+
+use std::fs;
+use std::ops::Deref;
+
+use memmap2::Mmap;
+
+enum Storage {
+    Mapped(Mmap),
+    Read(Vec<u8>),
+}
+
+pub struct MappedFile {
+    storage: Storage,
+}
+
+impl MappedFile {
+    /// # Safety
+    ///
+    /// The file must not be modified nor truncated while the MappedFile
+    /// exists, by this process or another one.
+    pub unsafe fn open(path: &str) -> Result<Self, String> {
+        let file = fs::File::open(path)
+            .map_err(|err| format!("{}: {}", path, err))?;
+        // SAFETY: the contract of open(), the file doesn't change.
+        match unsafe { Mmap::map(&file) } {
+            Ok(mmap) => Ok(MappedFile { storage: Storage::Mapped(mmap) }),
+            Err(_) => MappedFile::read(path),
+        }
+    }
+
+    pub fn read(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path)
+            .map_err(|err| format!("{}: {}", path, err))?;
+        Ok(MappedFile::from_bytes(bytes))
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        MappedFile { storage: Storage::Read(bytes) }
+    }
+
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.storage, Storage::Mapped(_))
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.storage {
+            Storage::Mapped(mmap) => mmap,
+            Storage::Read(bytes) => bytes,
+        }
+    }
+}
+
+// Little endian readers of the binary files, the readers check the size of
+// the sections when they open the file.
+pub fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+pub fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
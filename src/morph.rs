@@ -0,0 +1,171 @@
+/// Morphological analysis of the words with the HunSpell analyze() function.
+/// Each analysis is a line of "field:value" tokens, ex: for the pt_PT
+/// dictionary of Project Natura
+///
+///     casa  ->  " st:casa po:nc is:f is:s"
+///               " st:casar po:v is:ip is:3s"
+///
+/// where "st" is the stem (lemma), "po" the part of speech and "is" the
+/// inflection tags. The analyses of each word are kept in a cache, the
+/// analyze() function is called once per distinct word.
+///
+
+use std::collections::HashMap;
+
+use crate::spell_checker::SpellChecker;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MorphAnalysis {
+    pub stem: Option<String>,
+    pub pos: Option<String>,
+    pub inflections: Vec<String>,
+}
+
+impl MorphAnalysis {
+    // Parses one line of the analyze() output, the unknown fields are
+    // ignored.
+    pub fn parse(line: &str) -> Self {
+        let mut analysis = MorphAnalysis { stem: None, pos: None, inflections: Vec::new() };
+        for token in line.split_whitespace() {
+            match token.split_once(':') {
+                Some(("st", value)) if analysis.stem.is_none() => analysis.stem = Some(value.to_string()),
+                Some(("po", value)) if analysis.pos.is_none() => analysis.pos = Some(value.to_string()),
+                Some(("is", value)) => analysis.inflections.push(value.to_string()),
+                _ => (),
+            }
+        }
+        analysis
+    }
+}
+
+#[derive(Default)]
+pub struct MorphAnalyzer {
+    // Word -> distinct analyses.
+    cache: HashMap<String, Vec<MorphAnalysis>>,
+    // Word -> distinct lemmas.
+    lemma_cache: HashMap<String, Vec<String>>,
+}
+
+impl MorphAnalyzer {
+    pub fn new() -> Self {
+        MorphAnalyzer::default()
+    }
+
+    // The distinct analyses of the word, empty if HunSpell doesn't know it.
+    pub fn analyses(&mut self, spell_checker: &dyn SpellChecker, word: &str) -> &[MorphAnalysis] {
+        self.cache.entry(word.to_string()).or_insert_with(|| {
+            let mut res: Vec<MorphAnalysis> = Vec::new();
+            for line in spell_checker.analyze(word) {
+                let analysis = MorphAnalysis::parse(&line);
+                if !res.contains(&analysis) {
+                    res.push(analysis);
+                }
+            }
+            res
+        })
+    }
+
+    // The distinct lemmas of the word, sorted. The analyses without a stem
+    // use the stem() function, and a word without any stem is its own lemma.
+    pub fn lemmas(&mut self, spell_checker: &dyn SpellChecker, word: &str) -> &[String] {
+        if !self.lemma_cache.contains_key(word) {
+            let lemmas = self.find_lemmas(spell_checker, word);
+            self.lemma_cache.insert(word.to_string(), lemmas);
+        }
+        &self.lemma_cache[word]
+    }
+
+    fn find_lemmas(&mut self, spell_checker: &dyn SpellChecker, word: &str) -> Vec<String> {
+        let mut res: Vec<String> = Vec::new();
+        let mut flag_missing_stem = false;
+        for analysis in self.analyses(spell_checker, word) {
+            match &analysis.stem {
+                Some(stem) => res.push(stem.clone()),
+                None => flag_missing_stem = true,
+            }
+        }
+        if flag_missing_stem || res.is_empty() {
+            res.extend(spell_checker.stem(word));
+        }
+        if res.is_empty() {
+            res.push(word.to_string());
+        }
+        res.sort();
+        res.dedup();
+        res
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // The analyses and stems of a few words, also used by the lemma tests.
+    pub struct FakeAnalyzer {
+        pub analyses: HashMap<String, Vec<String>>,
+        pub stems: HashMap<String, Vec<String>>,
+    }
+
+    impl FakeAnalyzer {
+        pub fn new(analyses: &[(&str, &str)], stems: &[(&str, &str)]) -> Self {
+            let mut fake = FakeAnalyzer { analyses: HashMap::new(), stems: HashMap::new() };
+            for (word, line) in analyses {
+                fake.analyses.entry(word.to_string()).or_default().push(line.to_string());
+            }
+            for (word, stem) in stems {
+                fake.stems.entry(word.to_string()).or_default().push(stem.to_string());
+            }
+            fake
+        }
+    }
+
+    impl SpellChecker for FakeAnalyzer {
+        fn check(&self, word: &str) -> bool {
+            self.analyses.contains_key(word)
+        }
+
+        fn suggest(&self, _word: &str) -> Vec<String> {
+            Vec::new()
+        }
+
+        fn analyze(&self, word: &str) -> Vec<String> {
+            self.analyses.get(word).cloned().unwrap_or_default()
+        }
+
+        fn stem(&self, word: &str) -> Vec<String> {
+            self.stems.get(word).cloned().unwrap_or_default()
+        }
+    }
+
+    #[test]
+    fn parse_the_fields() {
+        assert_eq!(MorphAnalysis::parse(" st:casa po:nc is:f is:s"), MorphAnalysis {
+            stem: Some("casa".to_string()),
+            pos: Some("nc".to_string()),
+            inflections: vec!["f".to_string(), "s".to_string()],
+        });
+        // The first "st" and "po" win, the unknown fields are ignored.
+        assert_eq!(MorphAnalysis::parse("st:casar st:casa po:v po:nc al:casas xyz"), MorphAnalysis {
+            stem: Some("casar".to_string()),
+            pos: Some("v".to_string()),
+            inflections: Vec::new(),
+        });
+        assert_eq!(MorphAnalysis::parse(""), MorphAnalysis { stem: None, pos: None, inflections: Vec::new() });
+    }
+
+    #[test]
+    fn lemmas_of_the_analyses_and_of_stem() {
+        let fake = FakeAnalyzer::new(
+            &[("casa", " st:casa po:nc is:f"), ("casa", " st:casar po:v is:ip"), ("casa", " st:casa po:nc is:f"),
+              ("fui", " po:v"), ("casas", " st:casa po:nc")],
+            &[("fui", "ir"), ("fui", "ser")]);
+        let mut analyzer = MorphAnalyzer::new();
+        // The repeated analysis is kept once.
+        assert_eq!(analyzer.analyses(&fake, "casa").len(), 2);
+        assert_eq!(analyzer.lemmas(&fake, "casa"), ["casa", "casar"]);
+        assert_eq!(analyzer.lemmas(&fake, "casas"), ["casa"]);
+        // Without "st" the stem() function gives the lemmas.
+        assert_eq!(analyzer.lemmas(&fake, "fui"), ["ir", "ser"]);
+        assert_eq!(analyzer.lemmas(&fake, "xpto"), ["xpto"]);
+    }
+}
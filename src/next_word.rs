@@ -0,0 +1,207 @@
+/// Next word prediction over the words frequency and the n grams files, for
+/// keyboard suggestions:
+///
+///     top_k_next(["o", "parlamento"], 3)        -> europeu, e, não
+///     top_k_completions(Some("parlamento"), "eu", 3) -> europeu
+///
+/// The continuations of the longest context known are used first, then the
+/// ones of the shorter contexts (backoff) and at last the unigrams, until
+/// there are k words. Each word is given once, with the count of the order
+/// where it was found.
+///
+/// The n grams files can be of any order, the order of each line is the
+/// number of words of its key, ex: the "2_grams*.words" files.
+///
+/// The keys are case folded when loaded, as the text of the queries, and
+/// the counts of the case variants are merged, ex: "Alemanha" and
+/// "alemanha" are one word.
+///
+
+use std::collections::{HashMap, HashSet};
+
+use crate::casing;
+use crate::freq_dic;
+
+pub struct NextWordPredictor {
+    // Context (the n - 1 first words) -> next words sorted by descending
+    // count.
+    continuations: HashMap<Vec<String>, Vec<(String, u64)>>,
+    // Longest context of the n grams.
+    max_context_len: usize,
+    // Unigrams sorted by word, for the prefix search.
+    unigrams: Vec<(String, u64)>,
+    // Unigrams sorted by descending count, for the next words.
+    unigrams_by_count: Vec<(String, u64)>,
+}
+
+fn sort_by_count(words: &mut [(String, u64)]) {
+    words.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+}
+
+// Sorts by word and merges the counts of the same word.
+fn merge_counts(words: &mut Vec<(String, u64)>) {
+    words.sort();
+    words.dedup_by(|next, prev| {
+        if next.0 == prev.0 {
+            prev.1 += next.1;
+            true
+        } else {
+            false
+        }
+    });
+}
+
+impl NextWordPredictor {
+    pub fn new(word_freq: &HashMap<String, u64>) -> Self {
+        let mut unigrams: Vec<(String, u64)> = word_freq.iter()
+            .map(|(word, count)| (casing::case_fold(word), *count))
+            .collect();
+        merge_counts(&mut unigrams);
+        let mut unigrams_by_count = unigrams.clone();
+        sort_by_count(&mut unigrams_by_count);
+        NextWordPredictor {
+            continuations: HashMap::new(),
+            max_context_len: 0,
+            unigrams,
+            unigrams_by_count,
+        }
+    }
+
+    pub fn from_files(word_freq_path: &str, n_grams_paths: &[&str]) -> Result<Self, String> {
+        let word_freq = freq_dic::load_freq_file(word_freq_path)?;
+        let mut predictor = NextWordPredictor::new(&word_freq);
+        for path in n_grams_paths {
+            predictor.add_n_grams(&freq_dic::load_freq_file(path)?);
+        }
+        Ok(predictor)
+    }
+
+    // Adds the n grams, with keys of two or more words.
+    pub fn add_n_grams(&mut self, dic_n_grams: &HashMap<String, u64>) {
+        for (key, count) in dic_n_grams {
+            let mut words: Vec<String> = key.split(' ').map(casing::case_fold).collect();
+            if words.len() < 2 {
+                continue;
+            }
+            let next_word = words.pop().unwrap();
+            self.max_context_len = self.max_context_len.max(words.len());
+            self.continuations.entry(words).or_default().push((next_word, *count));
+        }
+        for next_words in self.continuations.values_mut() {
+            merge_counts(next_words);
+            sort_by_count(next_words);
+        }
+    }
+
+    // The continuations of the contexts from the longest to the shortest,
+    // then the unigrams.
+    fn backoff_candidates<'a>(&'a self, prefix_words: &[&str]) -> impl Iterator<Item = &'a (String, u64)> + 'a {
+        let start = prefix_words.len().saturating_sub(self.max_context_len);
+        let context: Vec<String> = prefix_words[start..].iter().map(|word| word.to_string()).collect();
+        let continuations = &self.continuations;
+        (0..context.len())
+            .filter_map(move |i| continuations.get(&context[i..]))
+            .flatten()
+    }
+
+    fn top_k_unigrams(&self, partial_prefix: &str, k: usize, seen: &HashSet<&str>) -> Vec<(String, u64)> {
+        if partial_prefix.is_empty() {
+            return self.unigrams_by_count.iter()
+                .filter(|(word, _)| !seen.contains(word.as_str()))
+                .take(k)
+                .cloned()
+                .collect();
+        }
+        let start = self.unigrams.partition_point(|(word, _)| word.as_str() < partial_prefix);
+        let mut res: Vec<(String, u64)> = self.unigrams[start..].iter()
+            .take_while(|(word, _)| word.starts_with(partial_prefix))
+            .filter(|(word, _)| !seen.contains(word.as_str()))
+            .cloned()
+            .collect();
+        sort_by_count(&mut res);
+        res.truncate(k);
+        res
+    }
+
+    fn top_k(&self, prefix_words: &[&str], partial_prefix: &str, k: usize) -> Vec<(String, u64)> {
+        let mut res: Vec<(String, u64)> = Vec::with_capacity(k);
+        let mut seen: HashSet<&str> = HashSet::new();
+        for (word, count) in self.backoff_candidates(prefix_words) {
+            if res.len() >= k {
+                return res;
+            }
+            if word.starts_with(partial_prefix) && seen.insert(word.as_str()) {
+                res.push((word.clone(), *count));
+            }
+        }
+        if res.len() < k {
+            let unigrams = self.top_k_unigrams(partial_prefix, k - res.len(), &seen);
+            res.extend(unigrams);
+        }
+        res
+    }
+
+    // The k most probable words after the prefix words.
+    pub fn top_k_next(&self, prefix_words: &[&str], k: usize) -> Vec<(String, u64)> {
+        self.top_k(prefix_words, "", k)
+    }
+
+    // The k most probable words that start with the partial prefix, after
+    // the previous word.
+    pub fn top_k_completions(&self, previous_word: Option<&str>, partial_prefix: &str, k: usize) -> Vec<(String, u64)> {
+        let prefix_words: Vec<&str> = previous_word.into_iter().collect();
+        self.top_k(&prefix_words, partial_prefix, k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn freq(pairs: &[(&str, u64)]) -> HashMap<String, u64> {
+        pairs.iter().map(|(key, count)| (key.to_string(), *count)).collect()
+    }
+
+    fn predictor() -> NextWordPredictor {
+        let mut predictor = NextWordPredictor::new(&freq(&[
+            ("o", 500), ("a", 400), ("parlamento", 60), ("europeu", 40), ("e", 300), ("Alemanha", 30),
+            ("alemanha", 2), ("alemão", 10), ("apoia", 8)]));
+        predictor.add_n_grams(&freq(&[("o parlamento", 50), ("parlamento europeu", 30), ("parlamento e", 10),
+                                      ("a Alemanha", 20), ("Alemanha apoia", 5), ("alemanha apoia", 1)]));
+        predictor
+    }
+
+    fn words(predictions: &[(String, u64)]) -> Vec<&str> {
+        predictions.iter().map(|(word, _)| word.as_str()).collect()
+    }
+
+    #[test]
+    fn backoff_from_the_2_grams_to_the_unigrams() {
+        let predictor = predictor();
+        assert_eq!(predictor.top_k_next(&["o", "parlamento"], 2),
+                   vec![("europeu".to_string(), 30), ("e".to_string(), 10)]);
+        // The 2 grams first, then the most frequent unigrams not given yet.
+        assert_eq!(words(&predictor.top_k_next(&["parlamento"], 4)), vec!["europeu", "e", "o", "a"]);
+        assert_eq!(words(&predictor.top_k_next(&["xyz"], 2)), vec!["o", "a"]);
+        assert_eq!(words(&predictor.top_k_next(&[], 3)), vec!["o", "a", "e"]);
+    }
+
+    #[test]
+    fn completions_of_a_partial_prefix() {
+        let predictor = predictor();
+        assert_eq!(words(&predictor.top_k_completions(Some("parlamento"), "eu", 3)), vec!["europeu"]);
+        assert_eq!(words(&predictor.top_k_completions(Some("parlamento"), "e", 3)), vec!["europeu", "e"]);
+        // The 2 grams of "a", then the unigrams with the prefix.
+        assert_eq!(words(&predictor.top_k_completions(Some("a"), "alem", 3)), vec!["alemanha", "alemão"]);
+        assert_eq!(words(&predictor.top_k_completions(None, "ap", 3)), vec!["apoia"]);
+        assert!(predictor.top_k_completions(None, "xyz", 3).is_empty());
+    }
+
+    #[test]
+    fn case_variants_are_merged() {
+        let predictor = predictor();
+        assert_eq!(predictor.top_k_next(&["alemanha"], 1), vec![("apoia".to_string(), 6)]);
+        assert_eq!(predictor.top_k_completions(None, "alem", 1), vec![("alemanha".to_string(), 32)]);
+        assert_eq!(predictor.top_k_next(&["a"], 1), vec![("alemanha".to_string(), 20)]);
+    }
+}
@@ -0,0 +1,171 @@
+/// The counter interface of the words and of the 2 grams, so the counting
+/// loops don't know where the counts are kept:
+///
+///     CountingMode::InMemory               -> IdCounter, all in a HashMap
+///     CountingMode::Spill { memory_budget }  -> SpillCounter (spill_counter.rs),
+///                                             sorted runs in temp files
+///     CountingMode::Approximate { .. }      -> ApproxCounter (approx_counter.rs),
+///                                             only the top k, with error bounds
+///
+/// The counts are written as the text files, "key count" lines in byte
+/// order of the key, the same of make_string_from_BTreeHap().
+///
+
+use std::fs;
+use std::io::{self, BufWriter, Write};
+
+use crate::approx_counter::{self, ApproxCounter};
+use crate::interned_counter::{IdCounter, Vocabulary};
+use crate::spill_counter::SpillCounter;
+
+// 256 MB of counts before a run is written to disk.
+pub const DEFAULT_MEMORY_BUDGET: usize = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CountingMode {
+    InMemory,
+    // Bytes of counts in memory, estimated, the vocabulary isn't included,
+    // see Vocabulary::num_bytes().
+    Spill { memory_budget: usize },
+    // The num_top most frequent, counts over by at most epsilon * the
+    // number of n grams, with probability 1 - delta. The counts use a fixed
+    // memory, the vocabulary isn't included.
+    Approximate { num_top: usize, epsilon: f64, delta: f64 },
+}
+
+pub trait NGramCounter<const N: usize> {
+    // Counts one n grams of the ids of the vocabulary.
+    fn count(&mut self, vocabulary: &Vocabulary, ids: [u32; N]) -> io::Result<()>;
+
+    // Writes all the counts, returns the number of lines.
+    fn write_sorted(&mut self, vocabulary: &Vocabulary, out: &mut dyn Write) -> io::Result<u64>;
+
+    // The error bounds of the counts, None if they are exact.
+    fn error_summary(&self) -> Option<String> {
+        None
+    }
+
+    // Tab separated file of the bounds of each count, None if they are exact.
+    fn make_bounds_string(&self, _vocabulary: &Vocabulary) -> Option<String> {
+        None
+    }
+
+    // The number of runs written to disk, 0 if all the counts were in memory.
+    fn num_runs(&self) -> usize {
+        0
+    }
+}
+
+pub fn make_counter<const N: usize>(counting_mode: CountingMode) -> Box<dyn NGramCounter<N>> {
+    match counting_mode {
+        CountingMode::InMemory => Box::new(IdCounter::<N>::new()),
+        CountingMode::Spill { memory_budget } => Box::new(SpillCounter::<N>::new(memory_budget)),
+        CountingMode::Approximate { num_top, epsilon, delta } => Box::new(ApproxCounter::<N>::new(num_top, epsilon, delta)),
+    }
+}
+
+pub fn write_count_line(out: &mut dyn Write, key: &str, count: u64) -> io::Result<()> {
+    writeln!(out, "{} {}", key, count)
+}
+
+// The same line of the ids, without making the key string.
+pub fn write_ids_line(out: &mut dyn Write, vocabulary: &Vocabulary, ids: &[u32], count: u64) -> io::Result<()> {
+    vocabulary.write_key(out, ids)?;
+    writeln!(out, " {}", count)
+}
+
+// The "num_top,epsilon,delta" of the Approximate counting, the defaults for
+// the empty or missing values, ex: "1000" or "1000,0.0001".
+pub fn parse_approximate(text: &str) -> Result<CountingMode, String> {
+    let error = || format!("bad approximate counting \"{}\", num_top,epsilon,delta", text);
+    let fields: Vec<&str> = text.split(',').map(|field| field.trim()).collect();
+    if fields.len() > 3 {
+        return Err(error());
+    }
+    let field = |i: usize| fields.get(i).copied().filter(|field| !field.is_empty());
+    let num_top = match field(0) {
+        Some(num_top) => num_top.parse::<usize>().ok().filter(|num_top| *num_top > 0).ok_or_else(error)?,
+        None => approx_counter::DEFAULT_NUM_TOP,
+    };
+    let probability = |i: usize, default: f64| match field(i) {
+        Some(value) => value.parse::<f64>().ok().filter(|value| *value > 0.0 && *value < 1.0).ok_or_else(error),
+        None => Ok(default),
+    };
+    let epsilon = probability(1, approx_counter::DEFAULT_EPSILON)?;
+    let delta = probability(2, approx_counter::DEFAULT_DELTA)?;
+    Ok(CountingMode::Approximate { num_top, epsilon, delta })
+}
+
+// The bytes of the memory budget, with an optional suffix K, M or G, the
+// default budget if empty.
+pub fn parse_memory_budget(text: &str) -> Result<usize, String> {
+    if text.is_empty() {
+        return Ok(DEFAULT_MEMORY_BUDGET);
+    }
+    let (number, unit) = match text.char_indices().last() {
+        Some((pos, 'K' | 'k')) => (&text[..pos], 1024),
+        Some((pos, 'M' | 'm')) => (&text[..pos], 1024 * 1024),
+        Some((pos, 'G' | 'g')) => (&text[..pos], 1024 * 1024 * 1024),
+        _ => (text, 1),
+    };
+    match number.parse::<usize>() {
+        Ok(number) if number > 0 => Ok(number * unit),
+        _ => Err(format!("bad memory budget \"{}\"", text)),
+    }
+}
+
+impl<const N: usize> NGramCounter<N> for IdCounter<N> {
+    fn count(&mut self, _vocabulary: &Vocabulary, ids: [u32; N]) -> io::Result<()> {
+        self.add(ids);
+        Ok(())
+    }
+
+    fn write_sorted(&mut self, vocabulary: &Vocabulary, out: &mut dyn Write) -> io::Result<u64> {
+        let counts = self.take_sorted(vocabulary);
+        for (ids, count) in &counts {
+            write_ids_line(out, vocabulary, ids, *count)?;
+        }
+        Ok(counts.len() as u64)
+    }
+}
+
+pub fn write_counts_file<const N: usize>(counter: &mut dyn NGramCounter<N>, vocabulary: &Vocabulary, path: &str) -> Result<u64, String> {
+    let error = |err: io::Error| format!("{}: {}", path, err);
+    let mut out = BufWriter::new(fs::File::create(path).map_err(error)?);
+    let num_lines = counter.write_sorted(vocabulary, &mut out).map_err(error)?;
+    out.flush().map_err(error)?;
+    Ok(num_lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approximate_options() {
+        let defaults = CountingMode::Approximate {
+            num_top: approx_counter::DEFAULT_NUM_TOP,
+            epsilon: approx_counter::DEFAULT_EPSILON,
+            delta: approx_counter::DEFAULT_DELTA,
+        };
+        assert_eq!(parse_approximate(""), Ok(defaults));
+        assert_eq!(parse_approximate("1000,,0.01"), Ok(CountingMode::Approximate {
+            num_top: 1000, epsilon: approx_counter::DEFAULT_EPSILON, delta: 0.01 }));
+        assert_eq!(parse_approximate("50, 0.001, 0.05"), Ok(CountingMode::Approximate { num_top: 50, epsilon: 0.001, delta: 0.05 }));
+        assert!(parse_approximate("0").is_err());
+        assert!(parse_approximate("10,2").is_err());
+        assert!(parse_approximate("10,0.1,0.1,0.1").is_err());
+    }
+
+    #[test]
+    fn memory_budgets() {
+        assert_eq!(parse_memory_budget(""), Ok(DEFAULT_MEMORY_BUDGET));
+        assert_eq!(parse_memory_budget("4096"), Ok(4096));
+        assert_eq!(parse_memory_budget("64K"), Ok(64 * 1024));
+        assert_eq!(parse_memory_budget("512M"), Ok(512 * 1024 * 1024));
+        assert_eq!(parse_memory_budget("2g"), Ok(2 * 1024 * 1024 * 1024));
+        assert!(parse_memory_budget("0").is_err());
+        assert!(parse_memory_budget("M").is_err());
+        assert!(parse_memory_budget("lots").is_err());
+    }
+}
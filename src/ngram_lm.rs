@@ -0,0 +1,433 @@
+/// N gram language model made from the sentences of the corpus, written in
+/// the ARPA format with the backoff weights:
+///
+///     \data\
+///     ngram 1=4163
+///     ngram 2=15599
+///
+///     \1-grams:
+///     -2.8451   comissão   -0.4372
+///     ...
+///     \end\
+///
+/// Each sentence starts with <s> and ends with </s>, the words out of the
+/// vocabulary are <unk>. Two smoothings, both interpolated with the lower
+/// order and, at the unigrams, with the uniform distribution:
+///
+///     Modified Kneser-Ney   (Chen and Goodman), three discounts D1, D2 and
+///                           D3+ for each order from the counts of counts,
+///                           and the lower orders with the continuation
+///                           counts N1+(. w), the number of distinct words
+///                           before the n gram.
+///
+///         p(w | h) = (c(h w) - D(c(h w))) / c(h) + g(h) * p(w | h')
+///         g(h)     = (D1 N1(h .) + D2 N2(h .) + D3+ N3+(h .)) / c(h)
+///
+///     Witten-Bell           with T(h) the number of distinct words after h.
+///
+///         p(w | h) = (c(h w) + T(h) * p(w | h')) / (c(h) + T(h))
+///
+/// The interpolated probabilities are written as they are, and the backoff
+/// weight of a context is g(h), so a backoff reader gives the same
+/// probabilities for the n grams that weren't seen.
+///
+/// An ARPA file can be read back to score text, with the backoff:
+///
+///     log p(w | h) = log p(h w)                   if h w is in the model
+///                  = bo(h) + log p(w | h')        if not
+///
+
+use std::fs;
+use std::collections::{BTreeMap, HashMap};
+
+pub const SENTENCE_START: &str = "<s>";
+pub const SENTENCE_END: &str = "</s>";
+pub const UNKNOWN_WORD: &str = "<unk>";
+
+// Log10 probability of the events that can't happen, ex: <s>.
+pub const LOG_PROB_ZERO: f64 = -99.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Smoothing {
+    ModifiedKneserNey,
+    WittenBell,
+}
+
+impl Smoothing {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "kn" => Some(Smoothing::ModifiedKneserNey),
+            "wb" => Some(Smoothing::WittenBell),
+            _ => None,
+        }
+    }
+}
+
+pub struct NGramCounts {
+    pub order: usize,
+    // The counts of the n grams of order k are at k - 1.
+    pub counts: Vec<HashMap<Vec<String>, u64>>,
+}
+
+impl NGramCounts {
+    pub fn new(order: usize) -> Self {
+        NGramCounts {
+            order,
+            counts: vec![HashMap::new(); order],
+        }
+    }
+
+    // Counts all the n grams of the sentence, with <s> and </s>. The <s>
+    // is counted as a unigram, it's the context of the first word.
+    pub fn add_sentence(&mut self, words: &[String]) {
+        let mut tokens: Vec<String> = Vec::with_capacity(words.len() + 2);
+        tokens.push(SENTENCE_START.to_string());
+        tokens.extend(words.iter().cloned());
+        tokens.push(SENTENCE_END.to_string());
+        for k in 1..=self.order {
+            for n_gram in tokens.windows(k) {
+                *self.counts[k - 1].entry(n_gram.to_vec()).or_insert(0) += 1;
+            }
+        }
+    }
+}
+
+// The statistics of the words after a context.
+#[derive(Debug, Default, Clone, Copy)]
+struct ContextStats {
+    total: f64,
+    num_types: [f64; 3],
+}
+
+impl ContextStats {
+    fn num_all_types(&self) -> f64 {
+        self.num_types.iter().sum()
+    }
+}
+
+// The three discounts of the modified Kneser-Ney, from the counts of counts.
+fn kneser_ney_discounts(counts: &HashMap<Vec<String>, u64>) -> [f64; 3] {
+    let mut count_of_counts = [0f64; 4];
+    for count in counts.values() {
+        if *count >= 1 && *count <= 4 {
+            count_of_counts[*count as usize - 1] += 1.0;
+        }
+    }
+    let [n1, n2, n3, n4] = count_of_counts;
+    if n1 == 0.0 || n2 == 0.0 || n3 == 0.0 || n4 == 0.0 {
+        // Too few n grams for the estimate.
+        return [0.5, 1.0, 1.5];
+    }
+    let y = n1 / (n1 + 2.0 * n2);
+    let discounts = [1.0 - 2.0 * y * n2 / n1, 2.0 - 3.0 * y * n3 / n2, 3.0 - 4.0 * y * n4 / n3];
+    // Each discount is kept between 0 and its count.
+    [discounts[0].clamp(0.0, 1.0), discounts[1].clamp(0.0, 2.0), discounts[2].clamp(0.0, 3.0)]
+}
+
+pub struct LanguageModel {
+    pub order: usize,
+    // Log10 probability and log10 backoff weight of the n grams of order k
+    // at k - 1.
+    pub n_grams: Vec<BTreeMap<Vec<String>, (f64, f64)>>,
+}
+
+impl LanguageModel {
+    pub fn build(counts: &NGramCounts, smoothing: Smoothing) -> Self {
+        let order = counts.order;
+
+        // The counts used at each order, the continuation counts of the
+        // Kneser-Ney for the lower orders, but not for the n grams that start
+        // with <s> that have nothing before.
+        let mut model_counts: Vec<HashMap<Vec<String>, u64>> = counts.counts.clone();
+        if smoothing == Smoothing::ModifiedKneserNey {
+            for k in 1..order {
+                let mut continuation: HashMap<Vec<String>, u64> = HashMap::new();
+                for n_gram in counts.counts[k].keys() {
+                    *continuation.entry(n_gram[1..].to_vec()).or_insert(0) += 1;
+                }
+                for (n_gram, count) in model_counts[k - 1].iter_mut() {
+                    if n_gram[0] != SENTENCE_START {
+                        *count = continuation.get(n_gram).copied().unwrap_or(0);
+                    }
+                }
+            }
+        }
+        // The <s> is never predicted.
+        model_counts[0].remove(&vec![SENTENCE_START.to_string()]);
+
+        // The interpolated probabilities, and the weights of the lower order
+        // of each context.
+        let mut probs: Vec<HashMap<Vec<String>, f64>> = vec![HashMap::new(); order];
+        let mut backoffs: Vec<HashMap<Vec<String>, f64>> = vec![HashMap::new(); order];
+
+        let mut vocab_size = model_counts[0].len() as f64;
+        if !model_counts[0].contains_key(&vec![UNKNOWN_WORD.to_string()]) {
+            vocab_size += 1.0;
+        }
+
+        for k in 1..=order {
+            let discounts = kneser_ney_discounts(&model_counts[k - 1]);
+            let discount = |count: u64| -> f64 {
+                match smoothing {
+                    Smoothing::ModifiedKneserNey => discounts[(count.min(3) - 1) as usize],
+                    Smoothing::WittenBell => 0.0,
+                }
+            };
+
+            let mut context_stats: HashMap<&[String], ContextStats> = HashMap::new();
+            for (n_gram, count) in &model_counts[k - 1] {
+                if *count == 0 {
+                    continue;
+                }
+                let stats = context_stats.entry(&n_gram[..k - 1]).or_default();
+                stats.total += *count as f64;
+                stats.num_types[((*count).min(3) - 1) as usize] += 1.0;
+            }
+
+            // The weight of the lower order of each context.
+            let gamma = |stats: &ContextStats| -> f64 {
+                match smoothing {
+                    Smoothing::ModifiedKneserNey => {
+                        (discounts[0] * stats.num_types[0] + discounts[1] * stats.num_types[1]
+                            + discounts[2] * stats.num_types[2]) / stats.total
+                    },
+                    Smoothing::WittenBell => stats.num_all_types() / (stats.total + stats.num_all_types()),
+                }
+            };
+
+            for (n_gram, count) in &model_counts[k - 1] {
+                let context = &n_gram[..k - 1];
+                let stats = match context_stats.get(context) {
+                    Some(stats) => stats,
+                    None => continue,
+                };
+                let alpha = match smoothing {
+                    Smoothing::ModifiedKneserNey if *count > 0 => (*count as f64 - discount(*count)) / stats.total,
+                    Smoothing::ModifiedKneserNey => 0.0,
+                    Smoothing::WittenBell => *count as f64 / (stats.total + stats.num_all_types()),
+                };
+                let lower_prob = if k == 1 {
+                    1.0 / vocab_size
+                } else {
+                    probs[k - 2].get(&n_gram[1..]).copied().unwrap_or(0.0)
+                };
+                probs[k - 1].insert(n_gram.clone(), alpha + gamma(stats) * lower_prob);
+            }
+            if k == 1 {
+                let empty_context: &[String] = &[];
+                let stats = context_stats.get(empty_context).copied().unwrap_or_default();
+                probs[0].entry(vec![UNKNOWN_WORD.to_string()]).or_insert(gamma(&stats) / vocab_size);
+            }
+            for (context, stats) in &context_stats {
+                if k > 1 {
+                    backoffs[k - 2].insert(context.to_vec(), gamma(stats));
+                }
+            }
+        }
+        probs[0].insert(vec![SENTENCE_START.to_string()], 0.0);
+
+        let mut n_grams: Vec<BTreeMap<Vec<String>, (f64, f64)>> = vec![BTreeMap::new(); order];
+        for k in 1..=order {
+            for (n_gram, prob) in &probs[k - 1] {
+                let log_prob = if *prob > 0.0 { prob.log10() } else { LOG_PROB_ZERO };
+                let log_backoff = match backoffs[k - 1].get(n_gram) {
+                    Some(backoff) if *backoff > 0.0 => backoff.log10(),
+                    _ => 0.0,
+                };
+                n_grams[k - 1].insert(n_gram.clone(), (log_prob, log_backoff));
+            }
+        }
+        LanguageModel { order, n_grams }
+    }
+
+    pub fn make_arpa_string(&self) -> String {
+        let mut res = String::from("\n\\data\\\n");
+        for (k, n_grams) in self.n_grams.iter().enumerate() {
+            res.push_str(&format!("ngram {}={}\n", k + 1, n_grams.len()));
+        }
+        for (k, n_grams) in self.n_grams.iter().enumerate() {
+            res.push_str(&format!("\n\\{}-grams:\n", k + 1));
+            for (n_gram, (log_prob, log_backoff)) in n_grams {
+                res.push_str(&format!("{:.6}\t{}", log_prob, n_gram.join(" ")));
+                if k + 1 < self.order {
+                    res.push_str(&format!("\t{:.6}", log_backoff));
+                }
+                res.push('\n');
+            }
+        }
+        res.push_str("\n\\end\\\n");
+        res
+    }
+
+    pub fn write_arpa(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.make_arpa_string())
+            .map_err(|err| format!("{}: {}", path, err))
+    }
+
+    pub fn parse_arpa(text: &str) -> Result<Self, String> {
+        let mut n_grams: Vec<BTreeMap<Vec<String>, (f64, f64)>> = Vec::new();
+        // Order of the section being read, 0 before the first one.
+        let mut k = 0;
+        for (num_line, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line == "\\data\\" || line.starts_with("ngram ") {
+                continue;
+            }
+            if line == "\\end\\" {
+                break;
+            }
+            if let Some(section) = line.strip_prefix('\\').and_then(|line| line.strip_suffix("-grams:")) {
+                k = section.parse::<usize>()
+                    .map_err(|_| format!("line {}: {}", num_line + 1, line))?;
+                while n_grams.len() < k {
+                    n_grams.push(BTreeMap::new());
+                }
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if k == 0 || fields.len() < k + 1 {
+                return Err(format!("line {}: {}", num_line + 1, line));
+            }
+            let log_prob = fields[0].parse::<f64>()
+                .map_err(|_| format!("line {}: {}", num_line + 1, line))?;
+            let log_backoff = match fields.get(k + 1) {
+                Some(field) => field.parse::<f64>()
+                    .map_err(|_| format!("line {}: {}", num_line + 1, line))?,
+                None => 0.0,
+            };
+            let n_gram: Vec<String> = fields[1..=k].iter().map(|word| word.to_string()).collect();
+            n_grams[k - 1].insert(n_gram, (log_prob, log_backoff));
+        }
+        if n_grams.is_empty() {
+            return Err("no n grams".to_string());
+        }
+        Ok(LanguageModel { order: n_grams.len(), n_grams })
+    }
+
+    pub fn from_arpa_file(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("{}: {}", path, err))?;
+        LanguageModel::parse_arpa(&text)
+            .map_err(|err| format!("{}: {}", path, err))
+    }
+
+    pub fn contains_word(&self, word: &str) -> bool {
+        self.n_grams[0].contains_key(&[word.to_string()][..])
+    }
+
+    // Log10 probability of the word after the context, with the backoff.
+    pub fn log_prob(&self, context: &[String], word: &str) -> f64 {
+        let start = context.len().saturating_sub(self.order - 1);
+        let mut n_gram: Vec<String> = context[start..].to_vec();
+        n_gram.push(word.to_string());
+
+        let mut log_backoff = 0.0;
+        for i in 0..n_gram.len() {
+            let suffix = &n_gram[i..];
+            if let Some((log_prob, _)) = self.n_grams[suffix.len() - 1].get(suffix) {
+                return log_backoff + log_prob;
+            }
+            if suffix.len() > 1 {
+                let suffix_context = &suffix[..suffix.len() - 1];
+                if let Some((_, context_log_backoff)) = self.n_grams[suffix_context.len() - 1].get(suffix_context) {
+                    log_backoff += context_log_backoff;
+                }
+            }
+        }
+        LOG_PROB_ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(order: usize) -> NGramCounts {
+        let mut counts = NGramCounts::new(order);
+        let text = "a comissão aprovou o relatório\n\
+                    a comissão rejeitou o relatório\n\
+                    o parlamento aprovou a proposta\n\
+                    o parlamento e a comissão\n\
+                    a proposta da comissão\n\
+                    o relatório da comissão foi aprovado";
+        for line in text.lines() {
+            let words: Vec<String> = line.split_whitespace().map(|word| word.to_string()).collect();
+            counts.add_sentence(&words);
+        }
+        counts
+    }
+
+    // The words that can follow a context, all but <s>.
+    fn vocabulary(model: &LanguageModel) -> Vec<String> {
+        model.n_grams[0].keys()
+            .map(|n_gram| n_gram[0].clone())
+            .filter(|word| word != SENTENCE_START)
+            .collect()
+    }
+
+    fn assert_distributions_sum_to_1(order: usize, smoothing: Smoothing) {
+        let counts = counts(order);
+        let model = LanguageModel::build(&counts, smoothing);
+        let vocabulary = vocabulary(&model);
+        // The empty context and the contexts seen in the corpus.
+        let mut contexts: Vec<Vec<String>> = vec![Vec::new()];
+        for k in 1..order {
+            contexts.extend(counts.counts[k - 1].keys().filter(|n_gram| n_gram[k - 1] != SENTENCE_END).cloned());
+        }
+        for context in &contexts {
+            let sum: f64 = vocabulary.iter().map(|word| 10f64.powf(model.log_prob(context, word))).sum();
+            assert!((sum - 1.0).abs() < 1e-9, "{:?} {:?}: {}", smoothing, context, sum);
+        }
+    }
+
+    #[test]
+    fn distributions_sum_to_1() {
+        for smoothing in [Smoothing::ModifiedKneserNey, Smoothing::WittenBell] {
+            for order in 1..=3 {
+                assert_distributions_sum_to_1(order, smoothing);
+            }
+        }
+    }
+
+    #[test]
+    fn arpa_round_trip() {
+        for smoothing in [Smoothing::ModifiedKneserNey, Smoothing::WittenBell] {
+            let model = LanguageModel::build(&counts(3), smoothing);
+            let parsed = LanguageModel::parse_arpa(&model.make_arpa_string()).unwrap();
+            assert_eq!(parsed.order, 3);
+            for k in 0..3 {
+                assert_eq!(parsed.n_grams[k].len(), model.n_grams[k].len());
+                for (n_gram, (log_prob, log_backoff)) in &model.n_grams[k] {
+                    let (parsed_log_prob, parsed_log_backoff) = parsed.n_grams[k][n_gram];
+                    assert!((parsed_log_prob - log_prob).abs() < 1e-6);
+                    // The highest order has no backoff weights.
+                    if k < 2 {
+                        assert!((parsed_log_backoff - log_backoff).abs() < 1e-6);
+                    }
+                }
+            }
+        }
+        assert!(LanguageModel::parse_arpa("\\data\\\n\\end\\\n").is_err());
+        assert!(LanguageModel::parse_arpa("\\1-grams:\nxyz a\n").is_err());
+    }
+
+    #[test]
+    fn backoff_of_the_unseen_n_grams() {
+        let model = LanguageModel::build(&counts(3), Smoothing::ModifiedKneserNey);
+        let words = |text: &str| -> Vec<String> { text.split_whitespace().map(|word| word.to_string()).collect() };
+        let entry = |text: &str| model.n_grams[words(text).len() - 1][&words(text)];
+
+        // "parlamento foi" isn't seen, the unigram "foi" with bo(parlamento).
+        assert!(!model.n_grams[1].contains_key(&words("parlamento foi")));
+        assert_eq!(model.log_prob(&words("parlamento"), "foi"), entry("parlamento").1 + entry("foi").0);
+        // "o parlamento foi", backs off twice.
+        assert_eq!(model.log_prob(&words("o parlamento"), "foi"),
+                   entry("o parlamento").1 + entry("parlamento").1 + entry("foi").0);
+        // A seen trigram has its own probability.
+        assert_eq!(model.log_prob(&words("o parlamento"), "aprovou"), entry("o parlamento aprovou").0);
+        // The unknown context words are skipped, the unknown words are <unk>.
+        assert_eq!(model.log_prob(&words("xyz"), "foi"), entry("foi").0);
+        assert_eq!(model.log_prob(&[], "xyz"), LOG_PROB_ZERO);
+        assert!(model.log_prob(&[], UNKNOWN_WORD) > LOG_PROB_ZERO);
+    }
+}
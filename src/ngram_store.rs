@@ -0,0 +1,294 @@
+/// Binary store of the words frequency and the 2 grams files, memory mapped
+/// at startup (see mmap_file.rs) instead of parsing the text files.
+///
+/// The words get integer ids in byte order, and the 2 grams are pairs of ids
+/// sorted by the first and then the second id, so a word and a 2 grams are
+/// found with binary searches, O(log n), and the 2 grams of a first word are
+/// a contiguous range.
+///
+/// Binary format, little endian:
+///
+///     magic        "PTNGRAM1"
+///     header       num_words, num_2_grams, blob_len          (u32 each)
+///     vocabulary   blob_offset u32, len u32, count u64       (num_words)
+///     2 grams      id_1 u32, id_2 u32, count u64             (num_2_grams)
+///     blob         the UTF-8 words
+///
+/// The count of a word is the one of the words frequency file, 0 for the
+/// words that are only in the 2 grams file. The order of the tables is
+/// checked when the store is opened.
+///
+
+use std::fs;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::freq_dic;
+use crate::mmap_file::{self, MappedFile};
+
+const MAGIC: &[u8; 8] = b"PTNGRAM1";
+const HEADER_LEN: usize = 8 + 3 * 4;
+const WORD_LEN: usize = 16;
+const PAIR_LEN: usize = 16;
+
+fn push_u32(bytes: &mut Vec<u8>, value: usize) {
+    bytes.extend_from_slice(&(value as u32).to_le_bytes());
+}
+
+// Makes the binary store of the words and the 2 grams counts.
+pub fn build_store(word_freq: &HashMap<String, u64>, dic_2_grams: &HashMap<String, u64>) -> Vec<u8> {
+    // Word -> count, in byte order, the position is the id.
+    let mut vocabulary: BTreeMap<&str, u64> = word_freq.iter().map(|(word, count)| (word.as_str(), *count)).collect();
+    let split_2_grams: Vec<(&str, &str, u64)> = dic_2_grams.iter()
+        .filter_map(|(words, count)| words.split_once(' ').map(|(word_1, word_2)| (word_1, word_2, *count)))
+        .filter(|(_, word_2, _)| !word_2.contains(' '))
+        .collect();
+    for (word_1, word_2, _) in &split_2_grams {
+        vocabulary.entry(word_1).or_insert(0);
+        vocabulary.entry(word_2).or_insert(0);
+    }
+    let ids: HashMap<&str, usize> = vocabulary.keys().enumerate().map(|(id, word)| (*word, id)).collect();
+
+    let mut pairs: Vec<(usize, usize, u64)> = split_2_grams.iter()
+        .map(|(word_1, word_2, count)| (ids[word_1], ids[word_2], *count))
+        .collect();
+    pairs.sort();
+
+    let blob_len: usize = vocabulary.keys().map(|word| word.len()).sum();
+    let mut bytes: Vec<u8> = Vec::with_capacity(HEADER_LEN + vocabulary.len() * WORD_LEN + pairs.len() * PAIR_LEN + blob_len);
+    bytes.extend_from_slice(MAGIC);
+    for value in [vocabulary.len(), pairs.len(), blob_len] {
+        push_u32(&mut bytes, value);
+    }
+    let mut blob_offset = 0;
+    for (word, count) in &vocabulary {
+        push_u32(&mut bytes, blob_offset);
+        push_u32(&mut bytes, word.len());
+        bytes.extend_from_slice(&count.to_le_bytes());
+        blob_offset += word.len();
+    }
+    for (id_1, id_2, count) in &pairs {
+        push_u32(&mut bytes, *id_1);
+        push_u32(&mut bytes, *id_2);
+        bytes.extend_from_slice(&count.to_le_bytes());
+    }
+    for word in vocabulary.keys() {
+        bytes.extend_from_slice(word.as_bytes());
+    }
+    bytes
+}
+
+pub fn build_store_file(word_freq_path: &str, dic_2_grams_path: &str, out_store_path: &str) -> Result<usize, String> {
+    let word_freq = freq_dic::load_freq_file(word_freq_path)?;
+    let dic_2_grams = freq_dic::load_freq_file(dic_2_grams_path)?;
+    let bytes = build_store(&word_freq, &dic_2_grams);
+    fs::write(out_store_path, &bytes)
+        .map_err(|err| format!("{}: {}", out_store_path, err))?;
+    Ok(bytes.len())
+}
+
+pub struct NGramStore {
+    data: MappedFile,
+    num_words: usize,
+    num_2_grams: usize,
+    words_offset: usize,
+    pairs_offset: usize,
+    blob_offset: usize,
+}
+
+impl NGramStore {
+    /// # Safety
+    ///
+    /// The one of MappedFile::open(), the file must not be modified nor
+    /// truncated while the store is open.
+    pub unsafe fn open(path: &str) -> Result<Self, String> {
+        // SAFETY: the contract of open().
+        let data = unsafe { MappedFile::open(path)? };
+        NGramStore::from_data(data)
+            .map_err(|err| format!("{}: {}", path, err))
+    }
+
+    pub fn from_data(data: MappedFile) -> Result<Self, String> {
+        if data.len() < HEADER_LEN || &data[..8] != MAGIC {
+            return Err("not an n grams store".to_string());
+        }
+        let num_words = mmap_file::read_u32(&data, 8) as usize;
+        let num_2_grams = mmap_file::read_u32(&data, 12) as usize;
+        let blob_len = mmap_file::read_u32(&data, 16) as usize;
+
+        let words_offset = HEADER_LEN;
+        let pairs_offset = words_offset + num_words * WORD_LEN;
+        let blob_offset = pairs_offset + num_2_grams * PAIR_LEN;
+        if data.len() != blob_offset + blob_len {
+            return Err("wrong size".to_string());
+        }
+        let store = NGramStore { data, num_words, num_2_grams, words_offset, pairs_offset, blob_offset };
+
+        // The binary searches need the words and the pairs sorted, and all
+        // the spans and ids inside the file.
+        let mut prev_word: Option<&[u8]> = None;
+        for id in 0..num_words {
+            let offset = words_offset + id * WORD_LEN;
+            let start = mmap_file::read_u32(&store.data, offset) as usize;
+            let len = mmap_file::read_u32(&store.data, offset + 4) as usize;
+            if start + len > blob_len {
+                return Err(format!("bad word {}", id));
+            }
+            let word = &store.data[blob_offset + start..blob_offset + start + len];
+            if std::str::from_utf8(word).is_err() || prev_word.is_some_and(|prev_word| prev_word >= word) {
+                return Err(format!("bad word {}", id));
+            }
+            prev_word = Some(word);
+        }
+        let mut prev_pair: Option<(u32, u32)> = None;
+        for index in 0..num_2_grams {
+            let (id_1, id_2, _) = store.pair(index);
+            if id_1 as usize >= num_words || id_2 as usize >= num_words || prev_pair.is_some_and(|prev_pair| prev_pair >= (id_1, id_2)) {
+                return Err(format!("bad 2 grams {}", index));
+            }
+            prev_pair = Some((id_1, id_2));
+        }
+        Ok(store)
+    }
+
+    pub fn num_words(&self) -> usize {
+        self.num_words
+    }
+
+    pub fn num_2_grams(&self) -> usize {
+        self.num_2_grams
+    }
+
+    pub fn is_mapped(&self) -> bool {
+        self.data.is_mapped()
+    }
+
+    // The word and its count.
+    pub fn word(&self, id: usize) -> (&str, u64) {
+        let offset = self.words_offset + id * WORD_LEN;
+        let start = self.blob_offset + mmap_file::read_u32(&self.data, offset) as usize;
+        let len = mmap_file::read_u32(&self.data, offset + 4) as usize;
+        let count = mmap_file::read_u64(&self.data, offset + 8);
+        (std::str::from_utf8(&self.data[start..start + len]).unwrap_or(""), count)
+    }
+
+    // Binary search of the word in the vocabulary.
+    pub fn word_id(&self, word: &str) -> Option<usize> {
+        let (mut low, mut high) = (0, self.num_words);
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.word(mid).0.as_bytes().cmp(word.as_bytes()) {
+                Ordering::Equal => return Some(mid),
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+            }
+        }
+        None
+    }
+
+    pub fn word_count(&self, word: &str) -> u64 {
+        self.word_id(word).map(|id| self.word(id).1).unwrap_or(0)
+    }
+
+    fn pair(&self, index: usize) -> (u32, u32, u64) {
+        let offset = self.pairs_offset + index * PAIR_LEN;
+        (mmap_file::read_u32(&self.data, offset),
+         mmap_file::read_u32(&self.data, offset + 4),
+         mmap_file::read_u64(&self.data, offset + 8))
+    }
+
+    // The first pair that isn't before (id_1, id_2).
+    fn lower_bound(&self, id_1: u32, id_2: u32) -> usize {
+        let (mut low, mut high) = (0, self.num_2_grams);
+        while low < high {
+            let mid = (low + high) / 2;
+            let (mid_1, mid_2, _) = self.pair(mid);
+            if (mid_1, mid_2) < (id_1, id_2) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    pub fn count_2_grams(&self, word_1: &str, word_2: &str) -> u64 {
+        let (id_1, id_2) = match (self.word_id(word_1), self.word_id(word_2)) {
+            (Some(id_1), Some(id_2)) => (id_1 as u32, id_2 as u32),
+            _ => return 0,
+        };
+        let index = self.lower_bound(id_1, id_2);
+        match (index < self.num_2_grams).then(|| self.pair(index)) {
+            Some((pair_1, pair_2, count)) if pair_1 == id_1 && pair_2 == id_2 => count,
+            _ => 0,
+        }
+    }
+
+    // The words after the first word with the counts of the 2 grams, in
+    // byte order.
+    pub fn next_words(&self, word_1: &str) -> Vec<(&str, u64)> {
+        let id_1 = match self.word_id(word_1) {
+            Some(id_1) => id_1 as u32,
+            None => return Vec::new(),
+        };
+        let start = self.lower_bound(id_1, 0);
+        let end = if id_1 == u32::MAX { self.num_2_grams } else { self.lower_bound(id_1 + 1, 0) };
+        (start..end)
+            .map(|index| {
+                let (_, id_2, count) = self.pair(index);
+                (self.word(id_2 as usize).0, count)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn freq_map(entries: &[(&str, u64)]) -> HashMap<String, u64> {
+        entries.iter().map(|(key, count)| (key.to_string(), *count)).collect()
+    }
+
+    fn make_store_bytes() -> Vec<u8> {
+        let word_freq = freq_map(&[("da", 40), ("comissão", 30), ("união", 20)]);
+        let dic_2_grams = freq_map(&[("da comissão", 12), ("da união", 7), ("comissão europeia", 5)]);
+        build_store(&word_freq, &dic_2_grams)
+    }
+
+    fn put_u32(bytes: &mut [u8], offset: usize, value: u32) {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    #[test]
+    fn counts_and_next_words() {
+        let store = NGramStore::from_data(MappedFile::from_bytes(make_store_bytes())).unwrap();
+        assert_eq!((store.num_words(), store.num_2_grams()), (4, 3));
+        assert_eq!(store.word_count("da"), 40);
+        assert_eq!(store.word_count("europeia"), 0);
+        assert_eq!(store.count_2_grams("da", "união"), 7);
+        assert_eq!(store.count_2_grams("união", "da"), 0);
+        assert_eq!(store.next_words("da"), vec![("comissão", 12), ("união", 7)]);
+    }
+
+    #[test]
+    fn corrupted_store_is_an_error() {
+        let bytes = make_store_bytes();
+        let num_words = mmap_file::read_u32(&bytes, 8) as usize;
+        let pairs_offset = HEADER_LEN + num_words * WORD_LEN;
+        let corrupt = |offset: usize, value: u32| {
+            let mut bytes = bytes.clone();
+            put_u32(&mut bytes, offset, value);
+            NGramStore::from_data(MappedFile::from_bytes(bytes))
+        };
+
+        assert!(NGramStore::from_data(MappedFile::from_bytes(bytes[..bytes.len() - 1].to_vec())).is_err());
+        // The length of the first word, past the blob, and the same word of
+        // the second, out of order.
+        assert!(corrupt(HEADER_LEN + 4, 1000).is_err());
+        assert!(corrupt(HEADER_LEN + WORD_LEN, 0).is_err());
+        // The ids of the pairs, out of the vocabulary and out of order.
+        assert!(corrupt(pairs_offset, num_words as u32).is_err());
+        assert!(corrupt(pairs_offset + PAIR_LEN, 0).is_err());
+    }
+}
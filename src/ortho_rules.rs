@@ -0,0 +1,524 @@
+/// Rule engine for the conversion from the pre-AO90 orthography to the
+/// AO90 orthography (Acordo Ortográfico de 1990).
+///
+/// The rules are declarative and ordered, they are loaded from a text file
+/// with one rule per line:
+///
+///     # Comment
+///     <rule_name> <kind> [arguments...]
+///
+/// The kinds of rules are:
+///
+///     capitalize_first                 alemanha     -> Alemanha
+///     all_upper_case                   opec         -> OPEC
+///     remove_char    c p               acta         -> ata
+///     remove_hyphen                    anti-semita  -> antissemita
+///     swap_char      ê:e á:a ...       pêlo         -> pelo
+///     accent_variants [confusion_file] orgao        -> órgão
+///     replace_word   pára:para ...     pára         -> para
+///     lowercase_word janeiro ...       Janeiro      -> janeiro
+///
+/// The first six kinds only generate candidates, a candidate is only
+/// accepted if it is one of the HunSpell suggestions for the word.
+/// The "replace_word" and "lowercase_word" kinds are explicit lists, so
+/// they are accepted without looking at the suggestions. The
+/// "lowercase_word" rules need the original case of the word, so they are
+/// applied before the case folding, see lower_case_match().
+///
+/// The accepted candidates of all the rules are ranked by a score that
+/// combines the type of edit, the priority of the rule (the order in the
+/// file) and the frequency of the candidate in the corpus:
+///
+///     score = edit_type_weight + rule_priority + ln(1 + freq)
+///
+/// where rule_priority is in ]0, 1] and the edit type weights are 1 apart,
+/// so the frequency can overcome both. Candidates with the same score as
+/// the best one are reported as ties.
+///
+
+use std::fs;
+use std::collections::HashMap;
+
+use crate::strings_extender::{StringUtils, StringUtilsVecChars};
+use crate::accent_confusion::AccentConfusion;
+
+#[derive(Debug, Clone)]
+pub enum RuleKind {
+    CapitalizeFirst,
+    AllUpperCase,
+    RemoveChar(Vec<char>),
+    RemoveHyphen,
+    SwapChar(HashMap<char, char>),
+    AccentVariants(AccentConfusion),
+    ReplaceWord(HashMap<String, String>),
+    LowercaseWord(Vec<String>),
+}
+
+// The type of edit made by a rule, from the most to the least trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditType {
+    Lexical,
+    Case,
+    SilentConsonant,
+    Hyphen,
+    Accent,
+}
+
+impl EditType {
+    pub fn weight(&self) -> f64 {
+        match self {
+            EditType::Lexical         => 5.0,
+            EditType::Case            => 4.0,
+            EditType::SilentConsonant => 3.0,
+            EditType::Hyphen          => 2.0,
+            EditType::Accent          => 1.0,
+        }
+    }
+}
+
+impl RuleKind {
+    pub fn edit_type(&self) -> EditType {
+        match self {
+            RuleKind::CapitalizeFirst | RuleKind::AllUpperCase | RuleKind::LowercaseWord(_) => EditType::Case,
+            RuleKind::RemoveChar(_)   => EditType::SilentConsonant,
+            RuleKind::RemoveHyphen    => EditType::Hyphen,
+            RuleKind::SwapChar(_)     => EditType::Accent,
+            RuleKind::AccentVariants(_) => EditType::Accent,
+            RuleKind::ReplaceWord(_)  => EditType::Lexical,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrthoRule {
+    pub name: String,
+    pub kind: RuleKind,
+}
+
+/// The result of a rule that fired, the name of the rule and the new word.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleMatch {
+    pub rule_name: String,
+    pub replacement: String,
+}
+
+// One accepted candidate of a rule, with its score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub rule_name: String,
+    pub replacement: String,
+    pub edit_type: EditType,
+    // Index of the rule in the rule set, 0 is the highest priority.
+    pub rule_index: usize,
+    pub freq: u64,
+    pub score: f64,
+}
+
+// The best candidate, the candidates with the same score and all the
+// candidates sorted by descending score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankedCorrection {
+    pub best: Candidate,
+    pub ties: Vec<Candidate>,
+    pub candidates: Vec<Candidate>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OrthoRuleSet {
+    pub rules: Vec<OrthoRule>,
+}
+
+impl OrthoRule {
+    pub fn new(name: &str, kind: RuleKind) -> Self {
+        OrthoRule { name: name.to_string(), kind }
+    }
+
+    // Parses one line of the rules file, ex: "silent_consonant remove_char c p".
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut tokens = line.split_whitespace();
+        let name = tokens.next().ok_or("missing rule name")?;
+        let kind_str = tokens.next().ok_or(format!("missing kind in rule \"{}\"", name))?;
+        let args: Vec<&str> = tokens.collect();
+
+        let kind = match kind_str {
+            "capitalize_first" => RuleKind::CapitalizeFirst,
+            "all_upper_case"   => RuleKind::AllUpperCase,
+            "remove_hyphen"    => RuleKind::RemoveHyphen,
+            "remove_char" => {
+                let mut chars = Vec::new();
+                for arg in &args {
+                    let arg_chars = arg.get_vec_chars();
+                    if arg_chars.len() != 1 {
+                        return Err(format!("rule \"{}\": \"{}\" isn't a single char", name, arg));
+                    }
+                    chars.push(arg_chars[0]);
+                }
+                RuleKind::RemoveChar(chars)
+            },
+            "swap_char" => {
+                let mut map = HashMap::new();
+                for arg in &args {
+                    let pair = parse_pair(name, arg)?;
+                    let from = pair.0.get_vec_chars();
+                    let to = pair.1.get_vec_chars();
+                    if from.len() != 1 || to.len() != 1 {
+                        return Err(format!("rule \"{}\": \"{}\" isn't a char pair", name, arg));
+                    }
+                    map.insert(from[0], to[0]);
+                }
+                RuleKind::SwapChar(map)
+            },
+            "accent_variants" => {
+                let accent_confusion = match args.first() {
+                    Some(path) => AccentConfusion::from_file(path)
+                        .map_err(|err| format!("rule \"{}\": {}", name, err))?,
+                    None => AccentConfusion::default_confusion(),
+                };
+                RuleKind::AccentVariants(accent_confusion)
+            },
+            "replace_word" => {
+                let mut map = HashMap::new();
+                for arg in &args {
+                    let (from, to) = parse_pair(name, arg)?;
+                    map.insert(from, to);
+                }
+                RuleKind::ReplaceWord(map)
+            },
+            "lowercase_word" => {
+                RuleKind::LowercaseWord(args.iter().map(|w| w.to_lowercase()).collect())
+            },
+            _ => return Err(format!("rule \"{}\": unknown kind \"{}\"", name, kind_str)),
+        };
+
+        Ok(OrthoRule::new(name, kind))
+    }
+
+    // Returns true if the candidates of this rule must be confirmed by the
+    // HunSpell suggestions.
+    pub fn needs_suggestion(&self) -> bool {
+        !matches!(self.kind, RuleKind::ReplaceWord(_) | RuleKind::LowercaseWord(_))
+    }
+
+    // Generates all the candidate words of this rule, in the order that they
+    // are tested.
+    pub fn candidates(&self, word: &str) -> Vec<String> {
+        let mut res: Vec<String> = Vec::new();
+        if word.is_empty() {
+            return res;
+        }
+        let mut tmp_string = String::with_capacity(50);
+
+        match &self.kind {
+            RuleKind::CapitalizeFirst => {
+                // Ex: alemanha vs Alemanha
+                let mut chars_vec = word.get_vec_chars();
+                let up_vec: Vec<_> = chars_vec[0].to_uppercase().collect();
+                chars_vec[0] = up_vec[0];
+                res.push(chars_vec.to_string_buf(&mut tmp_string).clone());
+            },
+            RuleKind::AllUpperCase => {
+                // Ex: opec vs OPEC
+                res.push(word.to_uppercase());
+            },
+            RuleKind::RemoveChar(chars) => {
+                // Ex: acta    vs ata
+                //     adopção vs adoção.
+                let mut chars_vec = word.get_vec_chars();
+                for i in 1..(chars_vec.len().saturating_sub(1)) {
+                    if chars.contains(&chars_vec[i]) {
+                        let ch = chars_vec.remove(i);
+                        res.push(chars_vec.to_string_buf(&mut tmp_string).clone());
+                        chars_vec.insert(i, ch);
+                    }
+                }
+            },
+            RuleKind::RemoveHyphen => {
+                // Ex: anti-semita  vs antissemita
+                //     auto-estrada vs autoestrada
+                let parts: Vec<&str> = word.split('-').collect();
+                for i in 1..parts.len() {
+                    let left = parts[..i].join("-");
+                    let right = parts[i..].join("-");
+                    let left_last = left.chars().last();
+                    let right_first = right.chars().next();
+                    if let (Some(l), Some(r)) = (left_last, right_first) {
+                        if is_vowel(l) && (r == 's' || r == 'r') {
+                            res.push(format!("{}{}{}", left, r, right));
+                        }
+                    }
+                    res.push(left + &right);
+                }
+            },
+            RuleKind::SwapChar(map) => {
+                // Ex: pêlo vs pelo
+                let mut chars_vec = word.get_vec_chars();
+                for i in 0..chars_vec.len() {
+                    if let Some(new_char) = map.get(&chars_vec[i]) {
+                        let old_char = chars_vec[i];
+                        chars_vec[i] = *new_char;
+                        res.push(chars_vec.to_string_buf(&mut tmp_string).clone());
+                        chars_vec[i] = old_char;
+                    }
+                }
+            },
+            RuleKind::AccentVariants(accent_confusion) => {
+                // Ex: orgao vs órgão, in order of cost.
+                for (variant, _cost) in accent_confusion.variants(word) {
+                    res.push(variant);
+                }
+            },
+            RuleKind::ReplaceWord(map) => {
+                // Ex: pára vs para
+                if let Some(new_word) = map.get(word) {
+                    res.push(new_word.clone());
+                }
+            },
+            RuleKind::LowercaseWord(words) => {
+                // Ex: Janeiro vs janeiro
+                let lower_case_word = word.to_lowercase();
+                if lower_case_word != word && words.contains(&lower_case_word) {
+                    res.push(lower_case_word);
+                }
+            },
+        }
+        res
+    }
+
+    // Applies only this rule to the word.
+    pub fn apply(&self, word: &str, suggestion_vec: &[String]) -> Option<RuleMatch> {
+        let flag_needs_suggestion = self.needs_suggestion();
+        for candidate in self.candidates(word) {
+            if !flag_needs_suggestion || suggestion_vec.contains(&candidate) {
+                return Some(RuleMatch {
+                    rule_name: self.name.clone(),
+                    replacement: candidate,
+                });
+            }
+        }
+        None
+    }
+}
+
+impl OrthoRuleSet {
+    // The rules that were hardcoded before the rules file existed.
+    pub fn default_rules() -> Self {
+        OrthoRuleSet {
+            rules: vec![
+                OrthoRule::new("capitalize_first", RuleKind::CapitalizeFirst),
+                OrthoRule::new("all_upper_case", RuleKind::AllUpperCase),
+                OrthoRule::new("silent_consonant", RuleKind::RemoveChar(vec!['c', 'p'])),
+                OrthoRule::new("accent_swap", RuleKind::SwapChar(crate::map_accents())),
+            ]
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for (line_num, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let rule = OrthoRule::parse(line)
+                .map_err(|err| format!("line {}: {}", line_num + 1, err))?;
+            rules.push(rule);
+        }
+        Ok(OrthoRuleSet { rules })
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("{}: {}", path, err))?;
+        OrthoRuleSet::parse(&text)
+            .map_err(|err| format!("{}: {}", path, err))
+    }
+
+    // Applies the "lowercase_word" rules to the original word, before the
+    // case folding, ex: Janeiro -> janeiro. The first rule that fires wins.
+    pub fn lower_case_match(&self, original: &str) -> Option<RuleMatch> {
+        self.rules.iter()
+            .filter(|rule| matches!(rule.kind, RuleKind::LowercaseWord(_)))
+            .find_map(|rule| rule.apply(original, &[]))
+    }
+
+    // Collects the accepted candidates of all the rules, each replacement
+    // only once with the best score. The rejected replacements are skipped.
+    pub fn candidates(&self, word: &str, suggestion_vec: &[String], word_freq: &HashMap<String, u64>,
+                      is_rejected: &dyn Fn(&str) -> bool) -> Vec<Candidate> {
+        let num_rules = self.rules.len() as f64;
+        let mut res: Vec<Candidate> = Vec::new();
+        for (rule_index, rule) in self.rules.iter().enumerate() {
+            let flag_needs_suggestion = rule.needs_suggestion();
+            let edit_type = rule.kind.edit_type();
+            for replacement in rule.candidates(word) {
+                if flag_needs_suggestion && !suggestion_vec.contains(&replacement) {
+                    continue;
+                }
+                if replacement == word || is_rejected(&replacement) || res.iter().any(|cand| cand.replacement == replacement) {
+                    // The previous candidate came from a rule with higher priority.
+                    continue;
+                }
+                let freq = word_freq.get(&replacement).copied().unwrap_or(0);
+                let rule_priority = (num_rules - rule_index as f64) / num_rules;
+                let score = edit_type.weight() + rule_priority + (1.0 + freq as f64).ln();
+                res.push(Candidate {
+                    rule_name: rule.name.clone(),
+                    replacement,
+                    edit_type,
+                    rule_index,
+                    freq,
+                    score,
+                });
+            }
+        }
+        res
+    }
+
+    // Ranks the candidates of all the rules, returns None if no rule fired.
+    pub fn rank(&self, word: &str, suggestion_vec: &[String], word_freq: &HashMap<String, u64>,
+                is_rejected: &dyn Fn(&str) -> bool) -> Option<RankedCorrection> {
+        let mut candidates = self.candidates(word, suggestion_vec, word_freq, is_rejected);
+        if candidates.is_empty() {
+            return None;
+        }
+        // Stable sort, so equal scores keep the order of the rules.
+        candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        let best = candidates[0].clone();
+        let ties: Vec<Candidate> = candidates[1..].iter()
+            .filter(|cand| (cand.score - best.score).abs() < 1e-9)
+            .cloned()
+            .collect();
+        Some(RankedCorrection { best, ties, candidates })
+    }
+}
+
+fn parse_pair(rule_name: &str, arg: &str) -> Result<(String, String), String> {
+    match arg.split_once(':') {
+        Some((from, to)) if !from.is_empty() && !to.is_empty() => Ok((from.to_string(), to.to_string())),
+        _ => Err(format!("rule \"{}\": \"{}\" isn't in the form from:to", rule_name, arg)),
+    }
+}
+
+fn is_vowel(ch: char) -> bool {
+    "aeiouáàâãéêíóôõú".contains(ch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(line: &str) -> OrthoRule {
+        OrthoRule::parse(line).unwrap()
+    }
+
+    fn suggestions(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn capitalize_first() {
+        let rule = rule("names capitalize_first");
+        assert_eq!(rule.candidates("alemanha"), vec!["Alemanha"]);
+        assert_eq!(rule.apply("alemanha", &suggestions(&["Alemanha"])).unwrap().replacement, "Alemanha");
+        assert_eq!(rule.apply("alemanha", &suggestions(&["alemã"])), None);
+    }
+
+    #[test]
+    fn all_upper_case() {
+        let rule = rule("short_forms all_upper_case");
+        assert_eq!(rule.apply("opec", &suggestions(&["OPEC"])).unwrap().replacement, "OPEC");
+        assert_eq!(rule.apply("opec", &[]), None);
+    }
+
+    #[test]
+    fn remove_char() {
+        let rule = rule("silent_consonant remove_char c p");
+        assert_eq!(rule.candidates("adopção"), vec!["adoção"]);
+        assert_eq!(rule.apply("acta", &suggestions(&["ata", "acto"])).unwrap().replacement, "ata");
+        // The first and the last chars aren't removed.
+        assert!(rule.candidates("cap").is_empty());
+        assert!(OrthoRule::parse("bad remove_char cp").is_err());
+    }
+
+    #[test]
+    fn remove_hyphen() {
+        let rule = rule("removed_hyphen remove_hyphen");
+        assert_eq!(rule.apply("anti-semita", &suggestions(&["antissemita"])).unwrap().replacement, "antissemita");
+        assert_eq!(rule.apply("auto-estrada", &suggestions(&["autoestrada"])).unwrap().replacement, "autoestrada");
+        assert!(rule.candidates("semita").is_empty());
+    }
+
+    #[test]
+    fn swap_char() {
+        let rule = rule("accent_swap swap_char ê:e á:a");
+        assert_eq!(rule.candidates("pêlo"), vec!["pelo"]);
+        assert_eq!(rule.apply("pêlo", &suggestions(&["pelo"])).unwrap().rule_name, "accent_swap");
+        assert!(OrthoRule::parse("bad swap_char ê").is_err());
+    }
+
+    #[test]
+    fn accent_variants() {
+        let rule = OrthoRule::new("accent_variants", RuleKind::AccentVariants(AccentConfusion::default_confusion()));
+        assert!(rule.candidates("nao").contains(&"não".to_string()));
+        assert_eq!(rule.apply("nao", &suggestions(&["não"])).unwrap().replacement, "não");
+        assert_eq!(rule.apply("nao", &[]), None);
+        assert_eq!(rule.apply("orgao", &suggestions(&["órgão", "orgia"])).unwrap().replacement, "órgão");
+    }
+
+    #[test]
+    fn replace_word() {
+        let rule = rule("removed_accent replace_word pára:para pêlo:pelo");
+        // Explicit list, the suggestions aren't needed.
+        assert_eq!(rule.apply("pára", &[]).unwrap().replacement, "para");
+        assert_eq!(rule.apply("para", &[]), None);
+        assert!(OrthoRule::parse("bad replace_word pára").is_err());
+    }
+
+    #[test]
+    fn lowercase_word() {
+        let rule = rule("lowercase_month lowercase_word Janeiro fevereiro");
+        assert_eq!(rule.apply("Janeiro", &[]).unwrap().replacement, "janeiro");
+        assert_eq!(rule.apply("FEVEREIRO", &[]).unwrap().replacement, "fevereiro");
+        // Already in lower case, the rule doesn't fire.
+        assert_eq!(rule.apply("janeiro", &[]), None);
+        assert_eq!(rule.apply("Março", &[]), None);
+    }
+
+    #[test]
+    fn lower_case_match_only_uses_lowercase_word_rules() {
+        let rule_set = OrthoRuleSet::parse("names capitalize_first\nlowercase_month lowercase_word janeiro\n").unwrap();
+        let rule_match = rule_set.lower_case_match("Janeiro").unwrap();
+        assert_eq!(rule_match.rule_name, "lowercase_month");
+        assert_eq!(rule_set.lower_case_match("alemanha"), None);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(OrthoRuleSet::parse("# comment\n\nnames capitalize_first\n").is_ok());
+        let err = OrthoRuleSet::parse("names capitalize_first\nbad unknown_kind\n").unwrap_err();
+        assert!(err.starts_with("line 2"));
+    }
+
+    #[test]
+    fn rank_by_edit_type_priority_and_freq() {
+        let rule_set = OrthoRuleSet::parse("accent_swap swap_char ê:e\nsilent_consonant remove_char c p\n").unwrap();
+        let word_freq: HashMap<String, u64> = HashMap::new();
+        let ranked = rule_set.rank("acta", &suggestions(&["ata"]), &word_freq, &|_| false).unwrap();
+        assert_eq!(ranked.best.rule_name, "silent_consonant");
+        assert_eq!(ranked.best.edit_type, EditType::SilentConsonant);
+        assert!(ranked.ties.is_empty());
+        assert!(rule_set.rank("casa", &suggestions(&["casa"]), &word_freq, &|_| false).is_none());
+    }
+
+    #[test]
+    fn rejected_replacements_are_skipped() {
+        let rule_set = OrthoRuleSet::parse("removed_accent replace_word pára:para
+names capitalize_first
+").unwrap();
+        let word_freq: HashMap<String, u64> = HashMap::new();
+        // The explicit lists don't need the suggestions, but the reject list still applies.
+        assert!(rule_set.rank("pára", &[], &word_freq, &|word| word == "para").is_none());
+        let ranked = rule_set.rank("alemanha", &suggestions(&["Alemanha"]), &word_freq, &|word| word == "para").unwrap();
+        assert_eq!(ranked.best.replacement, "Alemanha");
+    }
+}
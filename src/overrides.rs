@@ -0,0 +1,144 @@
+/// Manual overrides of the correction, consulted before HunSpell and
+/// before the AO90 rules. There are three files:
+///
+///   - forced mappings, one "original replacement" per line, the
+///     replacement is the rest of the line and can have several words,
+///     ex: "eurostat Eurostat", "sobretuddo sobre tudo"
+///   - always accept, one word per line, the word is kept as it is,
+///     ex: "facto"
+///   - always reject, one word per line, the word is never counted and
+///     is never accepted as a suggestion for other words.
+///
+/// Lines starting with '#' are comments. The words are case folded when
+/// loaded, as the words of the pipeline, the replacements of the forced
+/// mappings keep their case. A bad line is an error with its number, the
+/// overrides aren't used without it.
+///
+
+use std::fs;
+use std::collections::{HashMap, HashSet};
+
+use crate::casing;
+
+pub const RULE_OVERRIDE_MAP: &str    = "override_map";
+pub const RULE_OVERRIDE_ACCEPT: &str = "override_accept";
+pub const RULE_OVERRIDE_REJECT: &str = "override_reject";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OverrideAction {
+    Map(String),
+    Accept,
+    Reject,
+}
+
+// The paths of the three override files.
+#[derive(Debug, Clone)]
+pub struct OverrideFiles {
+    pub map_path: String,
+    pub accept_path: String,
+    pub reject_path: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub forced: HashMap<String, String>,
+    pub accept: HashSet<String>,
+    pub reject: HashSet<String>,
+}
+
+impl Overrides {
+    pub fn new() -> Self {
+        Overrides::default()
+    }
+
+    // Loads the three files, a file that doesn't exist is an empty list.
+    pub fn from_files(forced_path: &str, accept_path: &str, reject_path: &str) -> Result<Self, String> {
+        let mut overrides = Overrides::new();
+        overrides.forced = parse_forced(&read_optional(forced_path)?)
+            .map_err(|err| format!("{}: {}", forced_path, err))?;
+        overrides.accept = parse_list(&read_optional(accept_path)?);
+        overrides.reject = parse_list(&read_optional(reject_path)?);
+        Ok(overrides)
+    }
+
+    // The reject list wins over the accept list, and the accept list over
+    // the forced mappings.
+    pub fn lookup(&self, word: &str) -> Option<OverrideAction> {
+        if self.reject.contains(word) {
+            Some(OverrideAction::Reject)
+        } else if self.accept.contains(word) {
+            Some(OverrideAction::Accept)
+        } else {
+            self.forced.get(word).map(|replacement| OverrideAction::Map(replacement.clone()))
+        }
+    }
+
+    // Any candidate word, the suggestions can have upper case.
+    pub fn is_rejected(&self, word: &str) -> bool {
+        self.reject.contains(&casing::case_fold(word))
+    }
+}
+
+fn read_optional(path: &str) -> Result<String, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(text),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(err) => Err(format!("{}: {}", path, err)),
+    }
+}
+
+// The lines with the line numbers, without the comments and the empty ones.
+fn content_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(num_line, line)| (num_line + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+pub fn parse_forced(text: &str) -> Result<HashMap<String, String>, String> {
+    let mut forced = HashMap::new();
+    for (num_line, line) in content_lines(text) {
+        let (original, replacement) = line.split_once(char::is_whitespace)
+            .ok_or_else(|| format!("line {}: \"{}\" isn't in the form \"original replacement\"", num_line, line))?;
+        forced.insert(casing::case_fold(original), replacement.trim().to_string());
+    }
+    Ok(forced)
+}
+
+pub fn parse_list(text: &str) -> HashSet<String> {
+    content_lines(text).map(|(_, line)| casing::case_fold(line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_case_folded() {
+        let forced = parse_forced("# comment\nEuroStat Eurostat\n").unwrap();
+        assert_eq!(forced.get("eurostat").map(|word| word.as_str()), Some("Eurostat"));
+        let forced_words = parse_forced("Sobretuddo  sobre tudo \n").unwrap();
+        assert_eq!(forced_words.get("sobretuddo").map(|word| word.as_str()), Some("sobre tudo"));
+        let mut overrides = Overrides::new();
+        overrides.forced = forced;
+        overrides.accept = parse_list("Facto\n");
+        overrides.reject = parse_list("  Lixo  \n\n");
+        assert_eq!(overrides.lookup("facto"), Some(OverrideAction::Accept));
+        assert_eq!(overrides.lookup("eurostat"), Some(OverrideAction::Map("Eurostat".to_string())));
+        assert!(overrides.is_rejected("lixo"));
+        assert!(overrides.is_rejected("Lixo"));
+        assert!(!overrides.is_rejected("luxo"));
+    }
+
+    #[test]
+    fn reject_wins_over_accept_and_map() {
+        let mut overrides = Overrides::new();
+        overrides.forced = parse_forced("facto fato\n").unwrap();
+        overrides.accept = parse_list("facto\n");
+        assert_eq!(overrides.lookup("facto"), Some(OverrideAction::Accept));
+        overrides.reject = parse_list("FACTO\n");
+        assert_eq!(overrides.lookup("facto"), Some(OverrideAction::Reject));
+        assert_eq!(parse_forced("# comment\nfacto fato\nfacto\n"),
+                   Err("line 3: \"facto\" isn't in the form \"original replacement\"".to_string()));
+    }
+}
@@ -0,0 +1,139 @@
+/// Part of speech 1, 2 and 3 grams of the corrected words, with the "po"
+/// field of the HunSpell analysis (see morph.rs). Ex:
+///
+///     DET NOUN 5120
+///     NOUN ADJ 3410
+///
+/// The known tags of the pt_PT dictionary are mapped to the universal tags
+/// (nc -> NOUN, art -> DET), the other tags are kept in upper case. The
+/// words without analysis get the tag "UNK", as do the words that couldn't
+/// be corrected. A word with more then one tag gets the tags joined in one
+/// sorted key, ex: "NOUN|VERB" for "casa".
+///
+/// The n grams don't cross the end of a sentence, nor a rejected word.
+///
+
+use std::collections::BTreeMap;
+
+use crate::morph::MorphAnalyzer;
+use crate::spell_checker::SpellChecker;
+
+pub const UNKNOWN_TAG: &str = "UNK";
+pub const TAG_SEPARATOR: &str = "|";
+
+pub fn universal_tag(pos: &str) -> String {
+    let tag = match pos {
+        "nc" => "NOUN",
+        "np" => "PROPN",
+        "adj" => "ADJ",
+        "v" => "VERB",
+        "adv" => "ADV",
+        "art" => "DET",
+        "prep" => "ADP",
+        "pron" => "PRON",
+        "conj" => "CCONJ",
+        "num" => "NUM",
+        "interj" => "INTJ",
+        _ => return pos.to_uppercase(),
+    };
+    tag.to_string()
+}
+
+#[derive(Default)]
+pub struct PosNGramCounter {
+    analyzer: MorphAnalyzer,
+    // Tags of the last two words of the sentence.
+    window: Vec<String>,
+    pub pos_1_grams_freq: BTreeMap<String, u64>,
+    pub pos_2_grams_freq: BTreeMap<String, u64>,
+    pub pos_3_grams_freq: BTreeMap<String, u64>,
+    pub num_unknown: u64,
+    pub num_ambiguous: u64,
+}
+
+impl PosNGramCounter {
+    pub fn new() -> Self {
+        PosNGramCounter::default()
+    }
+
+    // The tag of the word, UNK or the sorted tags joined if there are many.
+    pub fn tag_of(&mut self, spell_checker: &dyn SpellChecker, word: &str) -> String {
+        let mut tags: Vec<String> = self.analyzer.analyses(spell_checker, word).iter()
+            .filter_map(|analysis| analysis.pos.as_deref())
+            .map(universal_tag)
+            .collect();
+        tags.sort();
+        tags.dedup();
+        if tags.is_empty() {
+            UNKNOWN_TAG.to_string()
+        } else {
+            tags.join(TAG_SEPARATOR)
+        }
+    }
+
+    // Counts the n grams that end in a corrected word.
+    pub fn add_word(&mut self, spell_checker: &dyn SpellChecker, word: &str) {
+        let tag = self.tag_of(spell_checker, word);
+        self.add_tag(tag);
+    }
+
+    // Counts the n grams that end in a word that couldn't be corrected.
+    pub fn add_unknown(&mut self) {
+        self.add_tag(UNKNOWN_TAG.to_string());
+    }
+
+    fn add_tag(&mut self, tag: String) {
+        if tag == UNKNOWN_TAG {
+            self.num_unknown += 1;
+        } else if tag.contains(TAG_SEPARATOR) {
+            self.num_ambiguous += 1;
+        }
+
+        *self.pos_1_grams_freq.entry(tag.clone()).or_insert(0) += 1;
+        if let Some(prev_tag) = self.window.last() {
+            let key = prev_tag.to_string() + " " + &tag;
+            *self.pos_2_grams_freq.entry(key).or_insert(0) += 1;
+        }
+        if self.window.len() == 2 {
+            let key = self.window.join(" ") + " " + &tag;
+            *self.pos_3_grams_freq.entry(key).or_insert(0) += 1;
+            self.window.remove(0);
+        }
+        self.window.push(tag);
+    }
+
+    // The next word starts new n grams.
+    pub fn end_sentence(&mut self) {
+        self.window.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn universal_tags() {
+        assert_eq!(universal_tag("nc"), "NOUN");
+        assert_eq!(universal_tag("np"), "PROPN");
+        assert_eq!(universal_tag("art"), "DET");
+        assert_eq!(universal_tag("prep"), "ADP");
+        assert_eq!(universal_tag("conj"), "CCONJ");
+        // The tags without a universal tag are kept in upper case.
+        assert_eq!(universal_tag("contr"), "CONTR");
+    }
+
+    #[test]
+    fn n_grams_stop_at_the_end_of_the_sentence() {
+        let mut counter = PosNGramCounter::new();
+        counter.add_tag("DET".to_string());
+        counter.add_tag("NOUN".to_string());
+        counter.add_tag("NOUN|VERB".to_string());
+        counter.end_sentence();
+        counter.add_unknown();
+        assert_eq!(counter.pos_1_grams_freq.len(), 4);
+        assert_eq!(counter.pos_2_grams_freq.keys().collect::<Vec<_>>(), vec!["DET NOUN", "NOUN NOUN|VERB"]);
+        assert_eq!(counter.pos_3_grams_freq.keys().collect::<Vec<_>>(), vec!["DET NOUN NOUN|VERB"]);
+        assert_eq!((counter.num_unknown, counter.num_ambiguous), (1, 1));
+    }
+}
@@ -0,0 +1,289 @@
+/// Interactive terminal review of the words that no rule could correct,
+/// the "not_check_dic*" file with lines "word -> sugg_1, sugg_2 count".
+/// A suggestion can have several words, ex: "sobre tudo".
+///
+/// The words are shown by descending frequency, with some sentences of
+/// the corpus where they appear and the HunSpell suggestions. For each
+/// word the reviewer can:
+///
+///     1..9   accept the suggestion with that number (forced mapping)
+///     a      accept the word as it is
+///     r      reject the word
+///     s      skip, decide later
+///     q      quit
+///
+/// Each decision is appended at once to the override files that the
+/// pipeline reads, see overrides.rs, so quitting doesn't lose the work.
+/// The words that already have an override aren't shown again.
+///
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::collections::HashMap;
+
+use regex::Regex;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::casing;
+use crate::freq_dic;
+use crate::overrides::{Overrides, OverrideFiles};
+
+// Max number of context sentences shown for each word.
+const MAX_CONTEXTS: usize = 3;
+
+// Between the suggestions of a line, they can have spaces inside.
+const SUGGESTIONS_SEPARATOR: &str = ", ";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectedWord {
+    pub word: String,
+    pub suggestions: Vec<String>,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    AcceptSuggestion(String),
+    AcceptWord,
+    Reject,
+    Skip,
+    Quit,
+}
+
+// The key of a line of the "not_check_dic*" file, without the count.
+pub fn make_not_check_key(word: &str, suggestions: &[String]) -> String {
+    word.to_string() + " -> " + &suggestions.join(SUGGESTIONS_SEPARATOR)
+}
+
+// Parses the "not_check_dic*" file, sorted by descending count.
+pub fn parse_not_check_text(text: &str) -> Vec<RejectedWord> {
+    let mut res: Vec<RejectedWord> = Vec::new();
+    for line in text.lines() {
+        if let Some((key, count)) = freq_dic::parse_freq_line(line) {
+            if let Some((word, suggestions)) = key.split_once(" -> ") {
+                res.push(RejectedWord {
+                    word: word.to_string(),
+                    suggestions: suggestions.split(SUGGESTIONS_SEPARATOR)
+                        .map(|s| s.trim())
+                        .filter(|s| !s.is_empty())
+                        .map(|s| s.to_string())
+                        .collect(),
+                    count,
+                });
+            }
+        }
+    }
+    res.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.word.cmp(&b.word)));
+    res
+}
+
+// Finds up to MAX_CONTEXTS sentences of the corpus for each word.
+pub fn find_contexts(corpus: &str, reg_ex_pt: &Regex, words: &[RejectedWord]) -> HashMap<String, Vec<String>> {
+    let mut contexts: HashMap<String, Vec<String>> = words.iter()
+        .map(|rejected| (rejected.word.clone(), Vec::new()))
+        .collect();
+    let mut num_missing = words.len();
+    for phrase in corpus.split('.') {
+        if num_missing == 0 {
+            break;
+        }
+        for word_only in reg_ex_pt.find_iter(phrase) {
+            let lower_case_word = casing::case_fold(word_only.as_str());
+            if let Some(word_contexts) = contexts.get_mut(&lower_case_word) {
+                let sentence = phrase.trim().to_string();
+                if word_contexts.len() < MAX_CONTEXTS && !word_contexts.contains(&sentence) {
+                    word_contexts.push(sentence);
+                    if word_contexts.len() == MAX_CONTEXTS {
+                        num_missing -= 1;
+                    }
+                }
+            }
+        }
+    }
+    contexts
+}
+
+pub fn parse_decision(input: &str, suggestions: &[String]) -> Option<Decision> {
+    let input = input.trim();
+    match input {
+        "a" => Some(Decision::AcceptWord),
+        "r" => Some(Decision::Reject),
+        "s" | "" => Some(Decision::Skip),
+        "q" => Some(Decision::Quit),
+        _ => {
+            let num = input.parse::<usize>().ok()?;
+            if (1..=9).contains(&num) && num <= suggestions.len() {
+                Some(Decision::AcceptSuggestion(suggestions[num - 1].clone()))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+fn append_line(path: &str, line: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
+
+// Writes the decision to the override file.
+pub fn save_decision(files: &OverrideFiles, word: &str, decision: &Decision) -> io::Result<()> {
+    match decision {
+        Decision::AcceptSuggestion(suggestion) => append_line(&files.map_path, &format!("{} {}", word, suggestion)),
+        Decision::AcceptWord => append_line(&files.accept_path, word),
+        Decision::Reject => append_line(&files.reject_path, word),
+        Decision::Skip | Decision::Quit => Ok(()),
+    }
+}
+
+// Marks the occurrences of the word in the sentence, matched on the case
+// folded text, the word is case folded, but the span marked keeps the case
+// of the sentence. Only whole words, "acta" isn't marked in "actas".
+pub fn mark_word(sentence: &str, word: &str) -> String {
+    let folded = casing::case_fold(word);
+    let is_word_char = |pos: usize| sentence[pos..].chars().next().is_some_and(|ch| ch.is_alphanumeric());
+    let bounds: Vec<usize> = sentence.char_indices().map(|(pos, _)| pos).chain(Some(sentence.len())).collect();
+    let mut res = String::new();
+    let mut last = 0;
+    for (i, start) in bounds.iter().enumerate() {
+        if *start < last || (i > 0 && is_word_char(bounds[i - 1])) {
+            continue;
+        }
+        // The folded span can be longer or shorter than the span.
+        let end = bounds[i + 1..].iter()
+            .take_while(|end| casing::case_fold(&sentence[*start..**end]).len() <= folded.len())
+            .find(|end| casing::case_fold(&sentence[*start..**end]) == folded && !is_word_char(**end));
+        if let Some(end) = end {
+            res.push_str(&sentence[last..*start]);
+            res.push_str(&format!("\x1b[1;33m{}\x1b[0m", &sentence[*start..*end]));
+            last = *end;
+        }
+    }
+    res.push_str(&sentence[last..]);
+    res
+}
+
+fn show_word<W: Write>(out: &mut W, index: usize, total: usize, rejected: &RejectedWord, contexts: &[String]) -> io::Result<()> {
+    // Clears the screen and moves the cursor to the top.
+    write!(out, "\x1b[2J\x1b[H")?;
+    writeln!(out, "[{}/{}]  \x1b[1m{}\x1b[0m  ({} occurrences)\n", index + 1, total, rejected.word, rejected.count)?;
+    writeln!(out, "Context:")?;
+    if contexts.is_empty() {
+        writeln!(out, "   (no sentences found)")?;
+    }
+    for sentence in contexts {
+        // Marks the word in the sentence.
+        let marked = mark_word(sentence, &rejected.word);
+        writeln!(out, "   ... {} ...", marked)?;
+    }
+    writeln!(out, "\nHunSpell suggestions:")?;
+    if rejected.suggestions.is_empty() {
+        writeln!(out, "   (none)")?;
+    }
+    for (i, suggestion) in rejected.suggestions.iter().enumerate().take(9) {
+        writeln!(out, "   {}. {}", i + 1, suggestion)?;
+    }
+    writeln!(out, "\n[1-9] accept suggestion   [a] accept word   [r] reject   [s] skip   [q] quit")?;
+    write!(out, "> ")?;
+    out.flush()
+}
+
+// Walks the words and asks for a decision for each one, returns the number
+// of decisions saved.
+pub fn review_loop<R: BufRead, W: Write>(words: &[RejectedWord], contexts: &HashMap<String, Vec<String>>,
+                                         files: &OverrideFiles, input: &mut R, out: &mut W) -> io::Result<usize> {
+    let empty: Vec<String> = Vec::new();
+    let mut num_saved = 0;
+    let mut line = String::new();
+    for (index, rejected) in words.iter().enumerate() {
+        let word_contexts = contexts.get(&rejected.word).unwrap_or(&empty);
+        let decision = loop {
+            show_word(out, index, words.len(), rejected, word_contexts)?;
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                // End of the input.
+                break Decision::Quit;
+            }
+            if let Some(decision) = parse_decision(&line, &rejected.suggestions) {
+                break decision;
+            }
+        };
+        if decision == Decision::Quit {
+            break;
+        }
+        if decision != Decision::Skip {
+            save_decision(files, &rejected.word, &decision)?;
+            num_saved += 1;
+        }
+    }
+    writeln!(out)?;
+    Ok(num_saved)
+}
+
+// The "review" sub command.
+pub fn review_not_check_file(not_check_path: &str, corpus_path: &str, reg_ex_pt: &Regex, files: OverrideFiles) {
+    let text = fs::read_to_string(not_check_path)
+        .expect("Something went wrong reading the not_check_dic file");
+
+    let overrides = Overrides::from_files(&files.map_path, &files.accept_path, &files.reject_path)
+        .expect("Something went wrong reading the overrides");
+
+    // The words already decided aren't shown again.
+    let words: Vec<RejectedWord> = parse_not_check_text(&text).into_iter()
+        .filter(|rejected| overrides.lookup(&rejected.word).is_none())
+        .collect();
+
+    let contexts = match fs::read_to_string(corpus_path) {
+        Ok(corpus) => find_contexts(&corpus.nfkc().collect::<String>(), reg_ex_pt, &words),
+        Err(err) => {
+            println!("\nError reading the corpus, no context sentences: {}", err);
+            HashMap::new()
+        }
+    };
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let res = review_loop(&words, &contexts, &files, &mut stdin.lock(), &mut stdout.lock());
+    match res {
+        Ok(num_saved) => println!("\nDecisions saved: {} of {} words.", num_saved, words.len()),
+        Err(err) => println!("\nError in the review: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marked(word: &str) -> String {
+        format!("\x1b[1;33m{}\x1b[0m", word)
+    }
+
+    #[test]
+    fn keeps_suggestions_with_several_words() {
+        let suggestions = vec!["sobre tudo".to_string(), "sobretudo".to_string()];
+        let text = format!("{} 3\nacta -> ata 7\nxpto ->  1\n", make_not_check_key("sobretuddo", &suggestions));
+        let words = parse_not_check_text(&text);
+        assert_eq!(words, vec![
+            RejectedWord { word: "acta".to_string(), suggestions: vec!["ata".to_string()], count: 7 },
+            RejectedWord { word: "sobretuddo".to_string(), suggestions: suggestions.clone(), count: 3 },
+            RejectedWord { word: "xpto".to_string(), suggestions: vec![], count: 1 },
+        ]);
+        assert_eq!(parse_decision("1", &words[1].suggestions),
+                   Some(Decision::AcceptSuggestion("sobre tudo".to_string())));
+        assert_eq!(parse_decision("2", &words[1].suggestions),
+                   Some(Decision::AcceptSuggestion("sobretudo".to_string())));
+    }
+
+    #[test]
+    fn marks_the_original_case() {
+        assert_eq!(mark_word("Acta da sessão, a acta foi aprovada", "acta"),
+                   format!("{} da sessão, a {} foi aprovada", marked("Acta"), marked("acta")));
+        assert_eq!(mark_word("A ACÇÃO comum", "acção"), format!("A {} comum", marked("ACÇÃO")));
+    }
+
+    #[test]
+    fn marks_only_whole_words() {
+        assert_eq!(mark_word("As actas e a reacta", "acta"), "As actas e a reacta");
+        assert_eq!(mark_word("pré-acta", "acta"), format!("pré-{}", marked("acta")));
+    }
+}
@@ -0,0 +1,54 @@
+/// The spell checker of the pipeline. With the "hunspell" cargo feature, on
+/// by default, it's the HunSpell C library (hunspell-rs). Without it, it's
+/// the native reader of the same dictionary (see affix_dic.rs), that only
+/// checks the words, it has no suggestions and no morphological analysis:
+///
+///     cargo build --release --no-default-features
+///
+
+use crate::affix_dic::NativeDictionary;
+
+pub trait SpellChecker {
+    fn check(&self, word: &str) -> bool;
+    fn suggest(&self, word: &str) -> Vec<String>;
+    // The lines of the morphological analysis, see morph.rs.
+    fn analyze(&self, word: &str) -> Vec<String>;
+    fn stem(&self, word: &str) -> Vec<String>;
+}
+
+#[cfg(feature = "hunspell")]
+impl SpellChecker for hunspell_rs::Hunspell {
+    fn check(&self, word: &str) -> bool {
+        hunspell_rs::Hunspell::check(self, word)
+    }
+
+    fn suggest(&self, word: &str) -> Vec<String> {
+        hunspell_rs::Hunspell::suggest(self, word)
+    }
+
+    fn analyze(&self, word: &str) -> Vec<String> {
+        hunspell_rs::Hunspell::analyze(self, word)
+    }
+
+    fn stem(&self, word: &str) -> Vec<String> {
+        hunspell_rs::Hunspell::stem(self, word)
+    }
+}
+
+impl SpellChecker for NativeDictionary {
+    fn check(&self, word: &str) -> bool {
+        NativeDictionary::check(self, word)
+    }
+
+    fn suggest(&self, _word: &str) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn analyze(&self, _word: &str) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn stem(&self, _word: &str) -> Vec<String> {
+        Vec::new()
+    }
+}
@@ -0,0 +1,201 @@
+/// Counter with a bounded memory, for the corpora where the counts don't
+/// fit in RAM.
+///
+/// The counts are kept in an IdCounter until its estimated size reaches the
+/// memory budget, then they are written sorted by key to a temp file (a
+/// run) and the counter is cleared. At the end the runs are merged with a
+/// k-way merge over a heap, reading one line of each run at a time, and the
+/// counts of the same key in many runs are summed, so the file written is
+/// the same of the in memory counter.
+///
+/// The budget is of the counts, and of the Vec where they are sorted, the
+/// vocabulary of the words isn't included, it grows with the number of
+/// distinct words.
+///
+/// Run files, in the temp dir, removed after the merge:
+///
+///     ngram_counts_<pid>_<counter>_<run>.run     "key count" lines
+///
+
+use std::fs;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::interned_counter::{IdCounter, Vocabulary};
+use crate::ngram_counter::{self, NGramCounter};
+
+// Hash table bytes per entry, besides the key and the count.
+const ENTRY_OVERHEAD: usize = 16;
+
+static NEXT_COUNTER_ID: AtomicUsize = AtomicUsize::new(0);
+
+pub struct SpillCounter<const N: usize> {
+    counts: IdCounter<N>,
+    max_entries: usize,
+    counter_id: usize,
+    run_paths: Vec<PathBuf>,
+    // All the runs written, the files are removed after the merge.
+    num_runs: usize,
+}
+
+impl<const N: usize> SpillCounter<N> {
+    pub fn new(memory_budget: usize) -> Self {
+        // The entry of the hash table and the one of the Vec of the sort.
+        let entry_size = std::mem::size_of::<[u32; N]>() + std::mem::size_of::<u64>() + ENTRY_OVERHEAD
+            + std::mem::size_of::<([u32; N], u64)>();
+        SpillCounter {
+            counts: IdCounter::new(),
+            max_entries: (memory_budget / entry_size).max(1),
+            counter_id: NEXT_COUNTER_ID.fetch_add(1, Ordering::Relaxed),
+            run_paths: Vec::new(),
+            num_runs: 0,
+        }
+    }
+
+    // Writes the counts in memory to a new run.
+    fn spill(&mut self, vocabulary: &Vocabulary) -> io::Result<()> {
+        let run_path = std::env::temp_dir().join(format!("ngram_counts_{}_{}_{}.run",
+            std::process::id(), self.counter_id, self.run_paths.len()));
+        let mut out = BufWriter::new(fs::File::create(&run_path)?);
+        self.run_paths.push(run_path);
+        self.num_runs += 1;
+        for (ids, count) in self.counts.take_sorted(vocabulary) {
+            ngram_counter::write_ids_line(&mut out, vocabulary, &ids, count)?;
+        }
+        out.flush()
+    }
+
+    fn remove_runs(&mut self) {
+        for run_path in self.run_paths.drain(..) {
+            let _res = fs::remove_file(run_path);
+        }
+    }
+
+    fn merge_runs(&mut self, out: &mut dyn Write) -> io::Result<u64> {
+        let mut runs: Vec<io::Lines<BufReader<fs::File>>> = Vec::with_capacity(self.run_paths.len());
+        for run_path in &self.run_paths {
+            runs.push(BufReader::new(fs::File::open(run_path)?).lines());
+        }
+
+        // The smallest key of each run, with the run index.
+        let mut heap: BinaryHeap<Reverse<(String, usize, u64)>> = BinaryHeap::with_capacity(runs.len());
+        for (index, run) in runs.iter_mut().enumerate() {
+            if let Some((key, count)) = next_count(run)? {
+                heap.push(Reverse((key, index, count)));
+            }
+        }
+
+        let mut num_lines = 0;
+        let mut current: Option<(String, u64)> = None;
+        while let Some(Reverse((key, index, count))) = heap.pop() {
+            match &mut current {
+                Some((current_key, current_count)) if *current_key == key => *current_count += count,
+                _ => {
+                    if let Some((current_key, current_count)) = current.take() {
+                        ngram_counter::write_count_line(out, &current_key, current_count)?;
+                        num_lines += 1;
+                    }
+                    current = Some((key, count));
+                },
+            }
+            if let Some((key, count)) = next_count(&mut runs[index])? {
+                heap.push(Reverse((key, index, count)));
+            }
+        }
+        if let Some((current_key, current_count)) = current {
+            ngram_counter::write_count_line(out, &current_key, current_count)?;
+            num_lines += 1;
+        }
+        Ok(num_lines)
+    }
+}
+
+// The next "key count" line of a run, the key can have spaces.
+fn next_count(run: &mut io::Lines<BufReader<fs::File>>) -> io::Result<Option<(String, u64)>> {
+    let line = match run.next() {
+        Some(line) => line?,
+        None => return Ok(None),
+    };
+    let bad_line = || io::Error::new(io::ErrorKind::InvalidData, format!("bad run line: {}", line));
+    let (key, count) = line.rsplit_once(' ').ok_or_else(bad_line)?;
+    let count = count.parse::<u64>().map_err(|_| bad_line())?;
+    Ok(Some((key.to_string(), count)))
+}
+
+impl<const N: usize> NGramCounter<N> for SpillCounter<N> {
+    fn count(&mut self, vocabulary: &Vocabulary, ids: [u32; N]) -> io::Result<()> {
+        self.counts.add(ids);
+        if self.counts.counts.len() >= self.max_entries {
+            self.spill(vocabulary)?;
+        }
+        Ok(())
+    }
+
+    fn write_sorted(&mut self, vocabulary: &Vocabulary, out: &mut dyn Write) -> io::Result<u64> {
+        if self.run_paths.is_empty() {
+            return self.counts.write_sorted(vocabulary, out);
+        }
+        if !self.counts.counts.is_empty() {
+            self.spill(vocabulary)?;
+        }
+        let res = self.merge_runs(out);
+        self.remove_runs();
+        res
+    }
+
+    fn num_runs(&self) -> usize {
+        self.num_runs
+    }
+}
+
+impl<const N: usize> Drop for SpillCounter<N> {
+    fn drop(&mut self) {
+        self.remove_runs();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_all<const N: usize>(counter: &mut dyn NGramCounter<N>, vocabulary: &Vocabulary, n_grams: &[[u32; N]]) -> Vec<u8> {
+        for ids in n_grams {
+            counter.count(vocabulary, *ids).unwrap();
+        }
+        let mut out: Vec<u8> = Vec::new();
+        counter.write_sorted(vocabulary, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn merged_runs_are_the_in_memory_file() {
+        let mut vocabulary = Vocabulary::new();
+        let words = ["a", "ab", "b", "comissão", "da", "é", "z"];
+        let ids: Vec<u32> = words.iter().map(|word| vocabulary.intern(word)).collect();
+        // A skewed stream, so the same keys are in many runs.
+        let n_grams: Vec<[u32; 2]> = (0..2000u32)
+            .map(|i| [ids[(i % 7) as usize], ids[((i * i) % 11 % 7) as usize]])
+            .collect();
+
+        let in_memory = write_all(&mut IdCounter::<2>::new(), &vocabulary, &n_grams);
+        // Budget of 3 entries.
+        let mut spill_counter = SpillCounter::<2>::new(3 * (12 + 8 + ENTRY_OVERHEAD + 16));
+        let spilled = write_all(&mut spill_counter, &vocabulary, &n_grams);
+        assert!(spill_counter.num_runs() > 10);
+        assert!(spill_counter.run_paths.is_empty());
+        assert_eq!(String::from_utf8(spilled).unwrap(), String::from_utf8(in_memory).unwrap());
+    }
+
+    #[test]
+    fn no_runs_within_the_budget() {
+        let mut vocabulary = Vocabulary::new();
+        let n_grams = [[vocabulary.intern("da")], [vocabulary.intern("a")], [vocabulary.intern("da")]];
+        let mut spill_counter = SpillCounter::<1>::new(ngram_counter::DEFAULT_MEMORY_BUDGET);
+        let spilled = write_all(&mut spill_counter, &vocabulary, &n_grams);
+        assert_eq!(spill_counter.num_runs(), 0);
+        assert_eq!(String::from_utf8(spilled).unwrap(), "a 1\nda 2\n");
+    }
+}
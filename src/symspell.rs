@@ -0,0 +1,405 @@
+/// SymSpell, spelling correction with the symmetric delete algorithm, over
+/// the words frequency file generated by this project (see reference 4).
+///
+/// For each word of the dictionary the deletes of its prefix, up to the max
+/// edit distance, are kept in an index. A lookup makes the deletes of the
+/// input and the words that share a delete with it are the candidates, the
+/// Damerau-Levenshtein distance (optimal string alignment) is only computed
+/// for them. The suggestions are sorted by distance and then by count.
+///
+/// The lookup_compound() corrects a whole sentence, with words split or
+/// joined by mistake, with the 2 grams file to choose between splitting a
+/// word or not.
+///
+
+use std::cmp::min;
+use std::collections::{HashMap, HashSet};
+
+use crate::freq_dic;
+
+pub const DEFAULT_MAX_EDIT_DISTANCE: usize = 2;
+pub const DEFAULT_PREFIX_LENGTH: usize = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    // The suggestion with the smallest distance and the highest count.
+    Top,
+    // All the suggestions with the smallest distance.
+    Closest,
+    // All the suggestions up to the max edit distance.
+    All,
+}
+
+impl Verbosity {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "top" => Some(Verbosity::Top),
+            "closest" => Some(Verbosity::Closest),
+            "all" => Some(Verbosity::All),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuggestItem {
+    pub term: String,
+    pub distance: usize,
+    pub count: u64,
+}
+
+// Damerau-Levenshtein distance, optimal string alignment.
+pub fn edit_distance(word_1: &str, word_2: &str) -> usize {
+    let chars_1: Vec<char> = word_1.chars().collect();
+    let chars_2: Vec<char> = word_2.chars().collect();
+    let (len_1, len_2) = (chars_1.len(), chars_2.len());
+    let mut dist = vec![vec![0usize; len_2 + 1]; len_1 + 1];
+    for (i, row) in dist.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in dist[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=len_1 {
+        for j in 1..=len_2 {
+            let cost = if chars_1[i - 1] == chars_2[j - 1] { 0 } else { 1 };
+            dist[i][j] = min(min(dist[i - 1][j] + 1, dist[i][j - 1] + 1), dist[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && chars_1[i - 1] == chars_2[j - 2] && chars_1[i - 2] == chars_2[j - 1] {
+                dist[i][j] = min(dist[i][j], dist[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    dist[len_1][len_2]
+}
+
+fn prefix(word: &str, prefix_length: usize) -> String {
+    word.chars().take(prefix_length).collect()
+}
+
+// The deletes of the word up to the max distance, without the word.
+fn make_deletes(word: &str, distance: usize, max_distance: usize, deletes: &mut HashSet<String>) {
+    if distance >= max_distance {
+        return;
+    }
+    let chars: Vec<char> = word.chars().collect();
+    for i in 0..chars.len() {
+        let delete: String = chars[..i].iter().chain(chars[i + 1..].iter()).collect();
+        if deletes.insert(delete.clone()) && distance + 1 < max_distance {
+            make_deletes(&delete, distance + 1, max_distance, deletes);
+        }
+    }
+}
+
+pub struct SymSpell {
+    pub max_dictionary_edit_distance: usize,
+    pub prefix_length: usize,
+    words: HashMap<String, u64>,
+    // Delete of the prefix -> words.
+    deletes: HashMap<String, Vec<String>>,
+    dic_2_grams: HashMap<String, u64>,
+    max_word_length: usize,
+    num_words: u64,
+    min_2_grams_count: u64,
+}
+
+impl SymSpell {
+    pub fn new(max_dictionary_edit_distance: usize, prefix_length: usize) -> Self {
+        SymSpell {
+            max_dictionary_edit_distance,
+            prefix_length: prefix_length.max(max_dictionary_edit_distance + 1),
+            words: HashMap::new(),
+            deletes: HashMap::new(),
+            dic_2_grams: HashMap::new(),
+            max_word_length: 0,
+            num_words: 0,
+            min_2_grams_count: u64::MAX,
+        }
+    }
+
+    pub fn from_word_freq(word_freq: &HashMap<String, u64>, max_dictionary_edit_distance: usize) -> Self {
+        let mut symspell = SymSpell::new(max_dictionary_edit_distance, DEFAULT_PREFIX_LENGTH);
+        for (word, count) in word_freq {
+            symspell.add_word(word, *count);
+        }
+        symspell
+    }
+
+    // Loads a "dic_corpus_unique*.words" file and optionally a "2_grams*.words" file.
+    pub fn from_files(word_freq_path: &str, dic_2_grams_path: Option<&str>, max_dictionary_edit_distance: usize) -> Result<Self, String> {
+        let word_freq = freq_dic::load_freq_file(word_freq_path)?;
+        let mut symspell = SymSpell::from_word_freq(&word_freq, max_dictionary_edit_distance);
+        if let Some(path) = dic_2_grams_path {
+            for (words, count) in freq_dic::load_freq_file(path)? {
+                symspell.add_2_grams(&words, count);
+            }
+        }
+        Ok(symspell)
+    }
+
+    pub fn add_word(&mut self, word: &str, count: u64) {
+        self.num_words += count;
+        if let Some(word_count) = self.words.get_mut(word) {
+            *word_count += count;
+            return;
+        }
+        self.words.insert(word.to_string(), count);
+        self.max_word_length = self.max_word_length.max(word.chars().count());
+
+        let word_prefix = prefix(word, self.prefix_length);
+        let mut deletes: HashSet<String> = HashSet::new();
+        make_deletes(&word_prefix, 0, self.max_dictionary_edit_distance, &mut deletes);
+        deletes.insert(word_prefix);
+        for delete in deletes {
+            self.deletes.entry(delete).or_default().push(word.to_string());
+        }
+    }
+
+    pub fn add_2_grams(&mut self, words: &str, count: u64) {
+        *self.dic_2_grams.entry(words.to_string()).or_insert(0) += count;
+        self.min_2_grams_count = self.min_2_grams_count.min(count);
+    }
+
+    pub fn word_count(&self, word: &str) -> Option<u64> {
+        self.words.get(word).copied()
+    }
+
+    pub fn lookup(&self, input: &str, verbosity: Verbosity, max_edit_distance: usize) -> Vec<SuggestItem> {
+        let max_edit_distance = max_edit_distance.min(self.max_dictionary_edit_distance);
+        let mut suggestions: Vec<SuggestItem> = Vec::new();
+        let input_len = input.chars().count();
+        if input_len > self.max_word_length + max_edit_distance {
+            return suggestions;
+        }
+
+        if let Some(count) = self.word_count(input) {
+            suggestions.push(SuggestItem { term: input.to_string(), distance: 0, count });
+            if verbosity != Verbosity::All {
+                return suggestions;
+            }
+        }
+        if max_edit_distance == 0 {
+            return suggestions;
+        }
+
+        // The smallest distance found, for Top and Closest.
+        let mut max_edit_distance_2 = max_edit_distance;
+        let mut considered_deletes: HashSet<String> = HashSet::new();
+        let mut considered_suggestions: HashSet<&str> = HashSet::new();
+        considered_suggestions.insert(input);
+
+        let input_prefix = prefix(input, self.prefix_length);
+        let input_prefix_len = input_prefix.chars().count();
+        let mut candidates: Vec<String> = vec![input_prefix];
+        let mut index = 0;
+        while index < candidates.len() {
+            let candidate = candidates[index].clone();
+            index += 1;
+            let candidate_len = candidate.chars().count();
+            let len_diff = input_prefix_len - candidate_len;
+            if len_diff > max_edit_distance_2 {
+                if verbosity == Verbosity::All {
+                    continue;
+                }
+                break;
+            }
+
+            if let Some(words) = self.deletes.get(&candidate) {
+                for sugg in words {
+                    if !considered_suggestions.insert(sugg.as_str()) {
+                        continue;
+                    }
+                    let sugg_len = sugg.chars().count();
+                    if sugg_len.abs_diff(input_len) > max_edit_distance_2 {
+                        continue;
+                    }
+                    let distance = edit_distance(input, sugg);
+                    if distance > max_edit_distance_2 {
+                        continue;
+                    }
+                    let item = SuggestItem { term: sugg.clone(), distance, count: self.words[sugg] };
+                    if !suggestions.is_empty() {
+                        match verbosity {
+                            Verbosity::Closest if distance < max_edit_distance_2 => suggestions.clear(),
+                            Verbosity::Top => {
+                                if distance < max_edit_distance_2 || item.count > suggestions[0].count {
+                                    max_edit_distance_2 = distance;
+                                    suggestions[0] = item;
+                                }
+                                continue;
+                            },
+                            _ => (),
+                        }
+                    }
+                    if verbosity != Verbosity::All {
+                        max_edit_distance_2 = distance;
+                    }
+                    suggestions.push(item);
+                }
+            }
+
+            // The next deletes of the input.
+            if len_diff < max_edit_distance && candidate_len <= self.prefix_length {
+                if verbosity != Verbosity::All && len_diff >= max_edit_distance_2 {
+                    continue;
+                }
+                let chars: Vec<char> = candidate.chars().collect();
+                for i in 0..chars.len() {
+                    let delete: String = chars[..i].iter().chain(chars[i + 1..].iter()).collect();
+                    if considered_deletes.insert(delete.clone()) {
+                        candidates.push(delete);
+                    }
+                }
+            }
+        }
+
+        suggestions.sort_by(|a, b| a.distance.cmp(&b.distance).then(b.count.cmp(&a.count)).then(a.term.cmp(&b.term)));
+        suggestions
+    }
+
+    // The count of two words together, from the 2 grams or estimated from
+    // the counts of each word.
+    fn count_2_grams(&self, word_1: &SuggestItem, word_2: &SuggestItem) -> u64 {
+        let key = word_1.term.clone() + " " + &word_2.term;
+        match self.dic_2_grams.get(&key) {
+            Some(count) => *count,
+            None => {
+                let estimate = word_1.count as f64 * word_2.count as f64 / self.num_words.max(1) as f64;
+                min(self.min_2_grams_count, estimate as u64)
+            },
+        }
+    }
+
+    fn unknown_item(&self, term: &str, max_edit_distance: usize) -> SuggestItem {
+        SuggestItem { term: term.to_string(), distance: max_edit_distance + 1, count: 0 }
+    }
+
+    // Corrects a text with many words, with the words split or joined by
+    // mistake. Gives one suggestion, the whole corrected text.
+    pub fn lookup_compound(&self, input: &str, max_edit_distance: usize) -> SuggestItem {
+        let terms: Vec<&str> = input.split_whitespace().collect();
+        let mut parts: Vec<SuggestItem> = Vec::new();
+        let mut flag_last_combi = false;
+
+        for (i, term) in terms.iter().enumerate() {
+            let suggestions = self.lookup(term, Verbosity::Top, max_edit_distance);
+
+            // Two terms that are one word split by mistake.
+            if i > 0 && !flag_last_combi {
+                let combi_term = terms[i - 1].to_string() + term;
+                let combi = self.lookup(&combi_term, Verbosity::Top, max_edit_distance);
+                if let (Some(best_combi), Some(best_1)) = (combi.first(), parts.last()) {
+                    let best_2 = suggestions.first().cloned()
+                        .unwrap_or_else(|| self.unknown_item(term, max_edit_distance));
+                    let distance_split = best_1.distance + best_2.distance;
+                    if best_combi.distance + 1 < distance_split
+                        || (best_combi.distance + 1 == distance_split && best_combi.count > self.count_2_grams(best_1, &best_2)) {
+                        let mut item = best_combi.clone();
+                        item.distance += 1;
+                        *parts.last_mut().unwrap() = item;
+                        flag_last_combi = true;
+                        continue;
+                    }
+                }
+            }
+            flag_last_combi = false;
+
+            if let Some(best) = suggestions.first() {
+                if best.distance == 0 || term.chars().count() == 1 {
+                    parts.push(best.clone());
+                    continue;
+                }
+            }
+
+            // One term that are two words joined by mistake.
+            let mut best_split: Option<SuggestItem> = suggestions.first().cloned();
+            let chars: Vec<char> = term.chars().collect();
+            for j in 1..chars.len() {
+                let part_1: String = chars[..j].iter().collect();
+                let part_2: String = chars[j..].iter().collect();
+                let sugg_1 = self.lookup(&part_1, Verbosity::Top, max_edit_distance);
+                let sugg_2 = self.lookup(&part_2, Verbosity::Top, max_edit_distance);
+                let (sugg_1, sugg_2) = match (sugg_1.first(), sugg_2.first()) {
+                    (Some(sugg_1), Some(sugg_2)) => (sugg_1, sugg_2),
+                    _ => continue,
+                };
+                let split_term = sugg_1.term.clone() + " " + &sugg_2.term;
+                let distance = edit_distance(term, &split_term);
+                let item = SuggestItem { term: split_term, distance, count: self.count_2_grams(sugg_1, sugg_2) };
+                let flag_better = match &best_split {
+                    Some(best) => distance < best.distance || (distance == best.distance && item.count > best.count),
+                    None => true,
+                };
+                if flag_better {
+                    best_split = Some(item);
+                }
+            }
+            parts.push(best_split.unwrap_or_else(|| self.unknown_item(term, max_edit_distance)));
+        }
+
+        let term = parts.iter().map(|part| part.term.as_str()).collect::<Vec<&str>>().join(" ");
+        let mut prob = 1.0;
+        for part in &parts {
+            prob *= part.count as f64 / self.num_words.max(1) as f64;
+        }
+        SuggestItem {
+            distance: edit_distance(input, &term),
+            count: (prob * self.num_words as f64) as u64,
+            term,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symspell() -> SymSpell {
+        let mut symspell = SymSpell::new(DEFAULT_MAX_EDIT_DISTANCE, DEFAULT_PREFIX_LENGTH);
+        for (word, count) in [("casa", 100), ("caso", 50), ("cada", 80), ("casas", 30), ("parlamento", 40),
+                              ("europeu", 20), ("o", 500), ("do", 400), ("sobre", 60), ("tudo", 60)] {
+            symspell.add_word(word, count);
+        }
+        symspell.add_2_grams("parlamento europeu", 15);
+        symspell
+    }
+
+    fn terms(items: &[SuggestItem]) -> Vec<(&str, usize)> {
+        items.iter().map(|item| (item.term.as_str(), item.distance)).collect()
+    }
+
+    #[test]
+    fn damerau_levenshtein_distance() {
+        assert_eq!(edit_distance("casa", "casa"), 0);
+        assert_eq!(edit_distance("casa", "cas"), 1);
+        assert_eq!(edit_distance("casa", "caso"), 1);
+        assert_eq!(edit_distance("casa", "csaa"), 1);
+        assert_eq!(edit_distance("ação", "acao"), 2);
+        assert_eq!(edit_distance("", "casa"), 4);
+        // Optimal string alignment, "ca" -> "ac" -> "abc" isn't allowed.
+        assert_eq!(edit_distance("ca", "abc"), 3);
+    }
+
+    #[test]
+    fn lookup_verbosity() {
+        let symspell = symspell();
+        assert_eq!(terms(&symspell.lookup("cas", Verbosity::Top, 2)), vec![("casa", 1)]);
+        assert_eq!(terms(&symspell.lookup("cas", Verbosity::Closest, 2)), vec![("casa", 1), ("caso", 1)]);
+        assert_eq!(terms(&symspell.lookup("cas", Verbosity::All, 2)),
+                   vec![("casa", 1), ("caso", 1), ("cada", 2), ("casas", 2)]);
+        // A known word is the only suggestion, but for All.
+        assert_eq!(terms(&symspell.lookup("casa", Verbosity::Closest, 2)), vec![("casa", 0)]);
+        assert_eq!(terms(&symspell.lookup("casa", Verbosity::All, 1)),
+                   vec![("casa", 0), ("cada", 1), ("caso", 1), ("casas", 1)]);
+        assert!(symspell.lookup("xyzxyz", Verbosity::All, 2).is_empty());
+        assert_eq!(Verbosity::parse("closest"), Some(Verbosity::Closest));
+        assert_eq!(Verbosity::parse("some"), None);
+    }
+
+    #[test]
+    fn lookup_compound_splits_and_joins() {
+        let symspell = symspell();
+        assert_eq!(symspell.lookup_compound("parlamentoeuropeu", 2).term, "parlamento europeu");
+        assert_eq!(symspell.lookup_compound("sobre tudo do parlamenro", 2).term, "sobre tudo do parlamento");
+        assert_eq!(symspell.lookup_compound("o parla mento", 2).term, "o parlamento");
+    }
+}
@@ -0,0 +1,165 @@
+/// Export of the words frequency and the 2 grams files in the formats that
+/// the SymSpell implementations load (see references 3 and 4):
+///
+///     frequency dictionary    "term count"            term index 0, count index 1
+///     bigram dictionary       "term_1 term_2 count"   term index 0, count index 2
+///
+/// One space as separator, "\n" line ends, UTF-8 without BOM and the lines
+/// sorted by descending count. The counts are loaded as Int64 in C#, the
+/// entries with a bigger count are written with i64::MAX. The entries that
+/// don't fit the format (a term with whitespace, a 2 grams without two
+/// terms, a count of zero) are skipped and counted.
+///
+/// After writing, each file is read back with a strict loader, that fails
+/// where a SymSpell loader would or on a negative count, and with the
+/// SymSpell of this project
+/// (see symspell.rs), and compared to the written entries.
+///
+
+use std::fs;
+use std::collections::HashMap;
+
+use crate::symspell::SymSpell;
+
+pub const SEPARATOR: &str = " ";
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExportStats {
+    pub num_written: u64,
+    pub num_skipped: u64,
+}
+
+// Makes the file text of the entries with the given number of terms, and
+// the entries that were written.
+pub fn make_symspell_string(dic_freq: &HashMap<String, u64>, num_terms: usize) -> (String, HashMap<String, u64>, ExportStats) {
+    let mut stats = ExportStats::default();
+    let mut entries: Vec<(&str, u64)> = Vec::with_capacity(dic_freq.len());
+    for (key, count) in dic_freq {
+        let terms: Vec<&str> = key.split(SEPARATOR).collect();
+        let flag_valid = terms.len() == num_terms
+            && terms.iter().all(|term| !term.is_empty() && !term.contains(char::is_whitespace))
+            && *count > 0;
+        if flag_valid {
+            entries.push((key, (*count).min(i64::MAX as u64)));
+        } else {
+            stats.num_skipped += 1;
+        }
+    }
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let mut res = String::with_capacity(20 * entries.len());
+    let mut written: HashMap<String, u64> = HashMap::with_capacity(entries.len());
+    for (key, count) in entries {
+        res.push_str(key);
+        res.push_str(SEPARATOR);
+        res.push_str(&count.to_string());
+        res.push('\n');
+        written.insert(key.to_string(), count);
+        stats.num_written += 1;
+    }
+    (res, written, stats)
+}
+
+// Strict loader of a SymSpell dictionary, the terms are joined with the
+// separator.
+pub fn parse_symspell_text(text: &str, num_terms: usize) -> Result<HashMap<String, u64>, String> {
+    if text.starts_with('\u{feff}') {
+        return Err("the file starts with a BOM".to_string());
+    }
+    let mut res: HashMap<String, u64> = HashMap::new();
+    for (num_line, line) in text.lines().enumerate() {
+        let error = |msg: &str| format!("line {}: {}: {}", num_line + 1, msg, line);
+        let parts: Vec<&str> = line.split(SEPARATOR).collect();
+        if parts.len() != num_terms + 1 {
+            return Err(error("wrong number of columns"));
+        }
+        if parts.iter().any(|part| part.is_empty() || part.contains(char::is_whitespace)) {
+            return Err(error("empty column or whitespace"));
+        }
+        let count = parts[num_terms].parse::<i64>()
+            .map_err(|_| error("count isn't an Int64"))?;
+        let count = u64::try_from(count)
+            .map_err(|_| error("negative count"))?;
+        let key = parts[..num_terms].join(SEPARATOR);
+        if res.insert(key, count).is_some() {
+            return Err(error("repeated term"));
+        }
+    }
+    Ok(res)
+}
+
+// Reads the file back and compares it to the written entries, with the
+// strict loader and with SymSpell.
+pub fn verify_symspell_file(path: &str, written: &HashMap<String, u64>, num_terms: usize) -> Result<(), String> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| format!("{}: {}", path, err))?;
+    let loaded = parse_symspell_text(&text, num_terms)
+        .map_err(|err| format!("{}: {}", path, err))?;
+    if &loaded != written {
+        return Err(format!("{}: {} entries loaded, {} written", path, loaded.len(), written.len()));
+    }
+
+    if num_terms == 1 {
+        let symspell = SymSpell::from_files(path, None, 0)?;
+        for (term, count) in written {
+            if symspell.word_count(term) != Some(*count) {
+                return Err(format!("{}: SymSpell count of \"{}\" isn't {}", path, term, count));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Writes the file and verifies it.
+pub fn export_symspell_file(dic_freq: &HashMap<String, u64>, num_terms: usize, out_path: &str) -> Result<ExportStats, String> {
+    let (text, written, stats) = make_symspell_string(dic_freq, num_terms);
+    fs::write(out_path, text)
+        .map_err(|err| format!("{}: {}", out_path, err))?;
+    verify_symspell_file(out_path, &written, num_terms)?;
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn freq(pairs: &[(&str, u64)]) -> HashMap<String, u64> {
+        pairs.iter().map(|(key, count)| (key.to_string(), *count)).collect()
+    }
+
+    #[test]
+    fn make_and_parse_round_trip() {
+        let dic_freq = freq(&[("casa", 10), ("de", 300), ("ação", 10), ("zero", 0), ("duas palavras", 5)]);
+        let (text, written, stats) = make_symspell_string(&dic_freq, 1);
+        assert_eq!(text, "de 300\nação 10\ncasa 10\n");
+        assert_eq!((stats.num_written, stats.num_skipped), (3, 2));
+        assert_eq!(parse_symspell_text(&text, 1).unwrap(), written);
+
+        let dic_2_grams = freq(&[("o parlamento", 7), ("uma", 3), ("a  b", 2), ("o muito", u64::MAX)]);
+        let (text, written, stats) = make_symspell_string(&dic_2_grams, 2);
+        assert_eq!(text, format!("o muito {}\no parlamento 7\n", i64::MAX));
+        assert_eq!((stats.num_written, stats.num_skipped), (2, 2));
+        assert_eq!(parse_symspell_text(&text, 2).unwrap(), written);
+    }
+
+    #[test]
+    fn strict_loader_errors() {
+        assert_eq!(parse_symspell_text("casa -3\n", 1).unwrap_err(), "line 1: negative count: casa -3");
+        assert!(parse_symspell_text("\u{feff}casa 3\n", 1).is_err());
+        assert!(parse_symspell_text("casa 3\ncasa 4\n", 1).is_err());
+        assert!(parse_symspell_text("casa 3 4\n", 1).is_err());
+        assert!(parse_symspell_text("casa  3\n", 1).is_err());
+        assert!(parse_symspell_text("casa 9223372036854775808\n", 1).is_err());
+    }
+
+    #[test]
+    fn export_and_verify_the_file() {
+        let out_path = std::env::temp_dir().join(format!("symspell_export_{}.txt", std::process::id()));
+        let out_path = out_path.to_str().unwrap();
+        let dic_freq = freq(&[("casa", 10), ("de", 300), ("", 4)]);
+        let stats = export_symspell_file(&dic_freq, 1, out_path).unwrap();
+        assert_eq!((stats.num_written, stats.num_skipped), (2, 1));
+        assert!(verify_symspell_file(out_path, &freq(&[("casa", 10)]), 1).is_err());
+        let _res = fs::remove_file(out_path);
+    }
+}
@@ -0,0 +1,82 @@
+/// Full text conversion from the pre-AO90 orthography to the AO90, with the
+/// same validation and mapping of the frequency files generation (see
+/// word_corrector.rs), but writing the converted text.
+///
+/// Only the words are changed, the punctuation and the whitespace between
+/// them are copied as they are. The correction is made on the case folded
+/// word and the corrected word gets the case pattern of the original word,
+/// see casing.rs.
+///
+/// The words that can't be corrected are kept as they are. The rules that
+/// lower case a word (ex: months) aren't applied to the first word of a
+/// sentence.
+///
+
+use regex::Regex;
+
+use crate::correction_audit::CorrectionAudit;
+use crate::casing::{self, CasedWord};
+use crate::word_corrector::{WordCorrector, Correction};
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConvertStats {
+    pub num_words: u64,
+    pub num_changed: u64,
+}
+
+// Converts one word, returns None if the word doesn't change.
+pub fn convert_word(original: &str, flag_sentence_start: bool, word_corrector: &mut WordCorrector,
+                    correction_audit: &mut CorrectionAudit) -> Option<String> {
+    // Ex: Janeiro -> janeiro, the pipeline lower case would hide it. The
+    // words of the overrides are left to correct().
+    if let Some(rule_match) = word_corrector.lower_case_rule(original, flag_sentence_start) {
+        correction_audit.record_rule_match(original, &rule_match);
+        return Some(rule_match.replacement);
+    }
+
+    let cased_word = CasedWord::new(original, flag_sentence_start);
+    let correction = word_corrector.correct(&cased_word.key);
+    correction_audit.record(&cased_word.key, correction);
+    let new_word = match correction {
+        Correction::Valid | Correction::Rejected | Correction::NotFound(_) => return None,
+        Correction::Overridden(rule_match) => cased_word.restore(&rule_match.replacement),
+        Correction::Corrected(ranked, _) => cased_word.restore(&ranked.best.replacement),
+    };
+
+    if new_word == original {
+        None
+    } else {
+        Some(new_word)
+    }
+}
+
+pub fn convert_text(text: &str, reg_ex_pt: &Regex, word_corrector: &mut WordCorrector,
+                    correction_audit: &mut CorrectionAudit) -> (String, ConvertStats) {
+    let mut res = String::with_capacity(text.len() + text.len() / 10);
+    let mut stats = ConvertStats::default();
+    let mut last_end = 0;
+    let mut flag_sentence_start = true;
+
+    for word_match in reg_ex_pt.find_iter(text) {
+        let gap = &text[last_end..word_match.start()];
+        if casing::ends_sentence(gap) {
+            flag_sentence_start = true;
+        }
+        res.push_str(gap);
+
+        let original = word_match.as_str();
+        stats.num_words += 1;
+        match convert_word(original, flag_sentence_start, word_corrector, correction_audit) {
+            Some(new_word) => {
+                stats.num_changed += 1;
+                res.push_str(&new_word);
+            },
+            None => res.push_str(original),
+        }
+
+        flag_sentence_start = false;
+        last_end = word_match.end();
+    }
+    res.push_str(&text[last_end..]);
+    (res, stats)
+}
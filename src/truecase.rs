@@ -0,0 +1,154 @@
+/// Truecasing model made from the corpus. For each word (case folded key)
+/// it counts the case variants seen in the middle of a sentence, the first
+/// word of a sentence isn't counted because there the Title case comes
+/// from the position. Ex:
+///
+///     alemanha Alemanha 3520
+///     comissão Comissão 8123
+///     comissão comissão 950
+///
+/// The table is written with lines "key surface count" and the Truecaser
+/// uses the most frequent surface of each key to restore the case of lower
+/// case text, capitalizing the first word of each sentence.
+///
+
+use std::fs;
+use std::collections::{BTreeMap, HashMap};
+
+use regex::Regex;
+
+use crate::casing;
+
+#[derive(Debug, Default)]
+pub struct TruecaseCounter {
+    // Key -> surface -> count.
+    pub counts: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+impl TruecaseCounter {
+    pub fn new() -> Self {
+        TruecaseCounter::default()
+    }
+
+    pub fn add(&mut self, surface: &str) {
+        let count = self.counts.entry(casing::case_fold(surface)).or_default()
+            .entry(surface.to_string()).or_insert(0);
+        *count += 1;
+    }
+
+    pub fn make_string(&self) -> String {
+        let mut res = String::with_capacity(30 * self.counts.len());
+        for (key, surfaces) in &self.counts {
+            for (surface, count) in surfaces {
+                res.push_str(key);
+                res.push(' ');
+                res.push_str(surface);
+                res.push(' ');
+                res.push_str(&count.to_string());
+                res.push('\n');
+            }
+        }
+        res
+    }
+}
+
+pub struct Truecaser {
+    // Key -> most frequent surface.
+    best_surface: HashMap<String, String>,
+    reg_ex_pt: Regex,
+}
+
+impl Truecaser {
+    pub fn parse(text: &str) -> Self {
+        let mut best: HashMap<String, (String, u64)> = HashMap::new();
+        for line in text.lines() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.len() != 3 {
+                continue;
+            }
+            let count = match tokens[2].parse::<u64>() {
+                Ok(count) => count,
+                Err(_) => continue,
+            };
+            let entry = best.entry(tokens[0].to_string()).or_insert((tokens[1].to_string(), 0));
+            // Ties keep the first surface, the lines are sorted.
+            if count > entry.1 {
+                *entry = (tokens[1].to_string(), count);
+            }
+        }
+        Truecaser {
+            best_surface: best.into_iter().map(|(key, (surface, _))| (key, surface)).collect(),
+            reg_ex_pt: crate::LangRegEx::new(crate::Lang::PT).reg_ex_comp_word_pattern,
+        }
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("{}: {}", path, err))?;
+        Ok(Truecaser::parse(&text))
+    }
+
+    pub fn truecase_word(&self, word: &str, flag_sentence_start: bool) -> String {
+        let key = casing::case_fold(word);
+        let surface = match self.best_surface.get(&key) {
+            Some(surface) => surface.clone(),
+            None => word.to_string(),
+        };
+        if flag_sentence_start {
+            casing::capitalize_first(&surface)
+        } else {
+            surface
+        }
+    }
+
+    // Restores the case of the words, keeps everything between them.
+    pub fn truecase_text(&self, text: &str) -> String {
+        let mut res = String::with_capacity(text.len());
+        let mut last_end = 0;
+        let mut flag_sentence_start = true;
+        for word_match in self.reg_ex_pt.find_iter(text) {
+            let gap = &text[last_end..word_match.start()];
+            if casing::ends_sentence(gap) {
+                flag_sentence_start = true;
+            }
+            res.push_str(gap);
+            res.push_str(&self.truecase_word(word_match.as_str(), flag_sentence_start));
+            flag_sentence_start = false;
+            last_end = word_match.end();
+        }
+        res.push_str(&text[last_end..]);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_the_case_variants() {
+        let mut truecase_counter = TruecaseCounter::new();
+        for surface in ["Comissão", "comissão", "Comissão"] {
+            truecase_counter.add(surface);
+        }
+        assert_eq!(truecase_counter.make_string(), "comissão Comissão 2\ncomissão comissão 1\n");
+    }
+
+    #[test]
+    fn uses_the_most_frequent_surface() {
+        let truecaser = Truecaser::parse("alemanha Alemanha 30\nalemanha alemanha 2\ncomissão Comissão 5\ncomissão comissão 5\nbad line\n");
+        assert_eq!(truecaser.truecase_word("alemanha", false), "Alemanha");
+        // Ties keep the first line.
+        assert_eq!(truecaser.truecase_word("comissão", false), "Comissão");
+        assert_eq!(truecaser.truecase_word("xpto", false), "xpto");
+        assert_eq!(truecaser.truecase_word("xpto", true), "Xpto");
+    }
+
+    #[test]
+    fn capitalizes_the_sentence_start() {
+        let truecaser = Truecaser::parse("alemanha Alemanha 30\nsessão sessão 4\n");
+        assert_eq!(truecaser.truecase_text("a alemanha. a sessão! porquê? a sessão: aberta"),
+                   "A Alemanha. A sessão! Porquê? A sessão: aberta");
+        assert_eq!(truecaser.truecase_text(""), "");
+    }
+}
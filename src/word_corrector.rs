@@ -0,0 +1,252 @@
+/// Correction of one word to the HunSpell pt_PT dictionary, shared by the
+/// words frequency and the 2 grams generation.
+///
+/// The manual overrides are consulted first. Then the words that HunSpell
+/// doesn't know are mapped with the AO90 rules over the HunSpell
+/// suggestions, no candidate of the rules can be in the reject list. The suggestions
+/// can come from SymSpell (see symspell.rs) instead of the HunSpell
+/// suggest() function. The suggest() function is slow, so the
+/// result for each unknown word is kept in a cache, with all the ranked
+/// candidates and not only the best one, so a later pass can still choose
+/// between them by the context.
+///
+
+use std::collections::HashMap;
+
+use crate::casing;
+use crate::ortho_rules::{OrthoRuleSet, RankedCorrection, RuleMatch};
+use crate::overrides::{self, Overrides, OverrideAction};
+use crate::spell_checker::SpellChecker;
+use crate::symspell::{SymSpell, Verbosity};
+
+// Max edit distance of the SymSpell suggestions.
+const SYMSPELL_MAX_EDIT_DISTANCE: usize = 2;
+
+#[derive(Debug, Clone)]
+pub enum Correction {
+    // The word is in the HunSpell dictionary.
+    Valid,
+    // The word is in the forced mappings or in the accept list.
+    Overridden(RuleMatch),
+    // The word is in the reject list.
+    Rejected,
+    // The word was mapped by the rules to a word in the dictionary, it has
+    // the HunSpell suggestions that were considered.
+    Corrected(RankedCorrection, Vec<String>),
+    // No rule found a substitution, it has the HunSpell suggestions.
+    NotFound(Vec<String>),
+}
+
+static CORRECTION_VALID: Correction = Correction::Valid;
+
+pub struct WordCorrector {
+    pub spell_checker: Box<dyn SpellChecker>,
+    pub ortho_rules: OrthoRuleSet,
+    pub word_freq: HashMap<String, u64>,
+    pub overrides: Overrides,
+    // The corrections of the overrides, made once.
+    override_corrections: HashMap<String, Correction>,
+    // HunSpell cache, only of the words that aren't in the dictionary.
+    pub hunspell_word_cache: HashMap<String, Correction>,
+    // If present, replaces the HunSpell suggest() function.
+    pub symspell: Option<SymSpell>,
+}
+
+impl WordCorrector {
+    pub fn new(spell_checker: Box<dyn SpellChecker>, ortho_rules: OrthoRuleSet, word_freq: HashMap<String, u64>, overrides: Overrides) -> Self {
+        let mut override_corrections: HashMap<String, Correction> = HashMap::new();
+        let words = overrides.forced.keys().chain(overrides.accept.iter()).chain(overrides.reject.iter());
+        for word in words {
+            let correction = match overrides.lookup(word) {
+                Some(OverrideAction::Map(replacement)) => Correction::Overridden(RuleMatch {
+                    rule_name: overrides::RULE_OVERRIDE_MAP.to_string(),
+                    replacement,
+                }),
+                Some(OverrideAction::Accept) => Correction::Overridden(RuleMatch {
+                    rule_name: overrides::RULE_OVERRIDE_ACCEPT.to_string(),
+                    replacement: word.clone(),
+                }),
+                Some(OverrideAction::Reject) | None => Correction::Rejected,
+            };
+            override_corrections.insert(word.clone(), correction);
+        }
+
+        WordCorrector {
+            spell_checker,
+            ortho_rules,
+            word_freq,
+            overrides,
+            override_corrections,
+            hunspell_word_cache: HashMap::new(),
+            symspell: None,
+        }
+    }
+
+    pub fn correct(&mut self, lower_case_word: &str) -> &Correction {
+        if let Some(correction) = self.override_corrections.get(lower_case_word) {
+            return correction;
+        }
+        if self.spell_checker.check(lower_case_word) {
+            return &CORRECTION_VALID;
+        }
+        // See if the correct map of the word to the hunspell dictionary is on the cache, if it is use it.
+        if !self.hunspell_word_cache.contains_key(lower_case_word) {
+            let mut suggestion_vec = self.suggest(lower_case_word);
+            suggestion_vec.retain(|sugg| !self.overrides.is_rejected(sugg));
+            let res = crate::from_old_treaty_to_new_treaty_orthographic(lower_case_word, &suggestion_vec, &self.ortho_rules, &self.word_freq, &self.overrides);
+            let correction = match res {
+                // Substituted the word for a correct word.
+                Some(ranked) => Correction::Corrected(ranked, suggestion_vec),
+                // Didn't found a correct substitution.
+                None => Correction::NotFound(suggestion_vec),
+            };
+            self.hunspell_word_cache.insert(lower_case_word.to_string(), correction);
+        }
+        &self.hunspell_word_cache[lower_case_word]
+    }
+
+    // The lower case rules (ex: Janeiro -> janeiro) need the original case,
+    // the key of correct() is already case folded. They aren't applied to
+    // the first word of a sentence, nor to the words of the overrides, that
+    // correct() handles, and the replacement can't be in the reject list.
+    pub fn lower_case_rule(&self, original: &str, flag_sentence_start: bool) -> Option<RuleMatch> {
+        if flag_sentence_start || self.override_corrections.contains_key(&casing::case_fold(original)) {
+            return None;
+        }
+        self.ortho_rules.lower_case_match(original)
+            .filter(|rule_match| !self.overrides.is_rejected(&rule_match.replacement))
+    }
+
+    fn suggest(&self, lower_case_word: &str) -> Vec<String> {
+        match &self.symspell {
+            Some(symspell) => symspell.lookup(lower_case_word, Verbosity::All, SYMSPELL_MAX_EDIT_DISTANCE)
+                .into_iter()
+                .map(|item| item.term)
+                .collect(),
+            None => self.spell_checker.suggest(lower_case_word),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::collections::HashSet;
+
+    // Knows a list of words, and counts the calls to suggest().
+    struct FakeChecker {
+        words: HashSet<String>,
+        suggestions: HashMap<String, Vec<String>>,
+        num_suggest: Rc<Cell<usize>>,
+    }
+
+    impl SpellChecker for FakeChecker {
+        fn check(&self, word: &str) -> bool {
+            self.words.contains(word)
+        }
+
+        fn suggest(&self, word: &str) -> Vec<String> {
+            self.num_suggest.set(self.num_suggest.get() + 1);
+            self.suggestions.get(word).cloned().unwrap_or_default()
+        }
+
+        fn analyze(&self, _word: &str) -> Vec<String> {
+            Vec::new()
+        }
+
+        fn stem(&self, _word: &str) -> Vec<String> {
+            Vec::new()
+        }
+    }
+
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    fn make_corrector(overrides: Overrides) -> (WordCorrector, Rc<Cell<usize>>) {
+        let num_suggest = Rc::new(Cell::new(0));
+        let checker = FakeChecker {
+            words: strings(&["ata", "ação", "para"]).into_iter().collect(),
+            suggestions: [("acta", strings(&["ata", "acta"])), ("xpto", strings(&["lixo", "xpta"]))]
+                .into_iter().map(|(word, suggestions)| (word.to_string(), suggestions)).collect(),
+            num_suggest: num_suggest.clone(),
+        };
+        let ortho_rules = OrthoRuleSet::parse("silent_consonant remove_char c p\nlowercase_month lowercase_word janeiro\n").unwrap();
+        (WordCorrector::new(Box::new(checker), ortho_rules, HashMap::new(), overrides), num_suggest)
+    }
+
+    #[test]
+    fn corrects_and_caches_the_unknown_words() {
+        let (mut word_corrector, num_suggest) = make_corrector(Overrides::new());
+        assert!(matches!(word_corrector.correct("ata"), Correction::Valid));
+        match word_corrector.correct("acta") {
+            Correction::Corrected(ranked, suggestions) => {
+                assert_eq!(ranked.best.rule_name, "silent_consonant");
+                assert_eq!(ranked.best.replacement, "ata");
+                assert_eq!(suggestions, &strings(&["ata", "acta"]));
+            },
+            correction => panic!("{:?}", correction),
+        }
+        assert!(matches!(word_corrector.correct("acta"), Correction::Corrected(..)));
+        assert!(matches!(word_corrector.correct("xpto"), Correction::NotFound(..)));
+        assert!(matches!(word_corrector.correct("xpto"), Correction::NotFound(..)));
+        // One call for each unknown word, the valid words aren't cached.
+        assert_eq!(num_suggest.get(), 2);
+        assert_eq!(word_corrector.hunspell_word_cache.len(), 2);
+    }
+
+    #[test]
+    fn overrides_come_first() {
+        let mut overrides = Overrides::new();
+        overrides.forced = [("acta".to_string(), "Acta".to_string())].into_iter().collect();
+        overrides.accept = ["xpto".to_string()].into_iter().collect();
+        overrides.reject = ["para".to_string(), "lixo".to_string()].into_iter().collect();
+        let (mut word_corrector, num_suggest) = make_corrector(overrides);
+        match word_corrector.correct("acta") {
+            Correction::Overridden(rule_match) => {
+                assert_eq!(rule_match.rule_name, overrides::RULE_OVERRIDE_MAP);
+                assert_eq!(rule_match.replacement, "Acta");
+            },
+            correction => panic!("{:?}", correction),
+        }
+        match word_corrector.correct("xpto") {
+            Correction::Overridden(rule_match) => assert_eq!(rule_match.rule_name, overrides::RULE_OVERRIDE_ACCEPT),
+            correction => panic!("{:?}", correction),
+        }
+        // Rejected even if the dictionary knows it.
+        assert!(matches!(word_corrector.correct("para"), Correction::Rejected));
+        assert_eq!(num_suggest.get(), 0);
+    }
+
+    #[test]
+    fn rejected_suggestions_are_removed() {
+        let mut overrides = Overrides::new();
+        overrides.reject = ["lixo".to_string()].into_iter().collect();
+        let (mut word_corrector, _) = make_corrector(overrides);
+        match word_corrector.correct("xpto") {
+            Correction::NotFound(suggestions) => assert_eq!(suggestions, &strings(&["xpta"])),
+            correction => panic!("{:?}", correction),
+        }
+    }
+
+    #[test]
+    fn lower_case_rule_not_at_the_sentence_start() {
+        let (word_corrector, _) = make_corrector(Overrides::new());
+        let rule_match = word_corrector.lower_case_rule("Janeiro", false).unwrap();
+        assert_eq!(rule_match.replacement, "janeiro");
+        assert_eq!(word_corrector.lower_case_rule("Janeiro", true), None);
+        assert_eq!(word_corrector.lower_case_rule("Acta", false), None);
+
+        let mut overrides = Overrides::new();
+        overrides.accept = ["janeiro".to_string()].into_iter().collect();
+        let (word_corrector, _) = make_corrector(overrides);
+        assert_eq!(word_corrector.lower_case_rule("Janeiro", false), None);
+
+        let mut overrides = Overrides::new();
+        overrides.reject = ["janeiro".to_string()].into_iter().collect();
+        let (word_corrector, _) = make_corrector(overrides);
+        assert_eq!(word_corrector.lower_case_rule("Janeiro", false), None);
+    }
+}
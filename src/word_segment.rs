@@ -0,0 +1,200 @@
+/// Word segmentation of text without spaces, ex: "parlamentoeuropeu" ->
+/// "parlamento europeu", the split with the highest probability with the
+/// unigram counts of the words frequency file:
+///
+///     P(w) = c(w) / N
+///     P(w) = 10 / (N * 10^len(w))     for the unknown words
+///
+/// The unknown words get a probability that falls with the length, so a
+/// long unknown word isn't chosen over the known words.
+///
+/// Optionally, with the 2 grams file, the split is rescored with the bigram
+/// model interpolated with the unigram model, the same of the diacritic
+/// restoration (see diacritic_restore.rs):
+///
+///     P(w_2 | w_1) = L * c(w_1 w_2) / c(w_1) + (1 - L) * P(w_2)
+///
+/// The counts are case folded.
+///
+
+use std::collections::HashMap;
+
+use crate::casing;
+use crate::freq_dic;
+
+// Weight of the bigram model in the interpolation.
+const BIGRAM_LAMBDA: f64 = 0.8;
+
+pub struct WordSegmenter {
+    // Case folded counts.
+    word_freq: HashMap<String, u64>,
+    dic_2_grams: Option<HashMap<String, u64>>,
+    num_words: u64,
+    max_word_length: usize,
+}
+
+impl WordSegmenter {
+    pub fn new(word_freq: &HashMap<String, u64>, dic_2_grams: Option<&HashMap<String, u64>>) -> Self {
+        let mut folded_word_freq: HashMap<String, u64> = HashMap::new();
+        for (word, count) in word_freq {
+            *folded_word_freq.entry(casing::case_fold(word)).or_insert(0) += count;
+        }
+        let folded_2_grams = dic_2_grams.map(|dic_2_grams| {
+            let mut folded_2_grams: HashMap<String, u64> = HashMap::new();
+            for (words, count) in dic_2_grams {
+                *folded_2_grams.entry(casing::case_fold(words)).or_insert(0) += count;
+            }
+            folded_2_grams
+        });
+        let num_words = folded_word_freq.values().sum::<u64>().max(1);
+        let max_word_length = folded_word_freq.keys().map(|word| word.chars().count()).max().unwrap_or(1);
+
+        WordSegmenter {
+            word_freq: folded_word_freq,
+            dic_2_grams: folded_2_grams,
+            num_words,
+            max_word_length,
+        }
+    }
+
+    pub fn from_files(word_freq_path: &str, dic_2_grams_path: Option<&str>) -> Result<Self, String> {
+        let word_freq = freq_dic::load_freq_file(word_freq_path)?;
+        let dic_2_grams = match dic_2_grams_path {
+            Some(path) => Some(freq_dic::load_freq_file(path)?),
+            None => None,
+        };
+        Ok(WordSegmenter::new(&word_freq, dic_2_grams.as_ref()))
+    }
+
+    fn log_prob_unigram(&self, word: &str) -> f64 {
+        let num_words = self.num_words as f64;
+        match self.word_freq.get(word) {
+            Some(count) => (*count as f64 / num_words).ln(),
+            None => 10f64.ln() - num_words.ln() - word.chars().count() as f64 * 10f64.ln(),
+        }
+    }
+
+    fn log_prob_bigram(&self, prev_word: &str, word: &str) -> f64 {
+        let dic_2_grams = match &self.dic_2_grams {
+            Some(dic_2_grams) => dic_2_grams,
+            None => return self.log_prob_unigram(word),
+        };
+        let prob_unigram = self.log_prob_unigram(word).exp();
+        let count_prev = self.word_freq.get(prev_word).copied().unwrap_or(0);
+        let mut prob_bigram = 0.0;
+        if count_prev > 0 {
+            let key = prev_word.to_string() + " " + word;
+            let count = dic_2_grams.get(&key).copied().unwrap_or(0);
+            prob_bigram = count as f64 / count_prev as f64;
+        }
+        (BIGRAM_LAMBDA * prob_bigram + (1.0 - BIGRAM_LAMBDA) * prob_unigram).ln()
+    }
+
+    // Segments one run of text without spaces. The state of the dynamic
+    // programming is the start of the last word, for the bigram model.
+    pub fn segment(&self, text: &str) -> Vec<String> {
+        let chars: Vec<char> = casing::case_fold(text).chars().collect();
+        let len = chars.len();
+        if len == 0 {
+            return Vec::new();
+        }
+        let word_of = |start: usize, end: usize| -> String { chars[start..end].iter().collect() };
+
+        // best[end][start] = (score, start of the previous word) of the best
+        // split of chars[..end] with the last word chars[start..end].
+        let mut best: Vec<HashMap<usize, (f64, usize)>> = vec![HashMap::new(); len + 1];
+        for end in 1..=len {
+            for start in end.saturating_sub(self.max_word_length)..end {
+                let word = word_of(start, end);
+                let entry = if start == 0 {
+                    (self.log_prob_unigram(&word), 0)
+                } else {
+                    let mut best_prev = (f64::NEG_INFINITY, 0);
+                    for (prev_start, (prev_score, _)) in &best[start] {
+                        let score = prev_score + self.log_prob_bigram(&word_of(*prev_start, start), &word);
+                        if score > best_prev.0 {
+                            best_prev = (score, *prev_start);
+                        }
+                    }
+                    best_prev
+                };
+                if entry.0 > f64::NEG_INFINITY {
+                    best[end].insert(start, entry);
+                }
+            }
+        }
+
+        // Follows the back pointers from the best last word.
+        let mut start = best[len].iter()
+            .max_by(|a, b| (a.1).0.total_cmp(&(b.1).0))
+            .map(|(start, _)| *start)
+            .unwrap_or(0);
+        let mut end = len;
+        let mut res: Vec<String> = Vec::new();
+        loop {
+            res.push(word_of(start, end));
+            if start == 0 {
+                break;
+            }
+            let prev_start = best[end][&start].1;
+            end = start;
+            start = prev_start;
+        }
+        res.reverse();
+        res
+    }
+
+    // Segments each whitespace separated token of the text.
+    pub fn segment_text(&self, text: &str) -> String {
+        text.split_whitespace()
+            .flat_map(|token| self.segment(token))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn freq(pairs: &[(&str, u64)]) -> HashMap<String, u64> {
+        pairs.iter().map(|(key, count)| (key.to_string(), *count)).collect()
+    }
+
+    fn word_freq() -> HashMap<String, u64> {
+        freq(&[("o", 500), ("parlamento", 40), ("europeu", 20), ("par", 30), ("lamento", 5), ("eu", 60), ("ropeu", 1)])
+    }
+
+    #[test]
+    fn splits_the_known_words() {
+        let word_segmenter = WordSegmenter::new(&word_freq(), None);
+        assert_eq!(word_segmenter.segment("parlamentoeuropeu"), vec!["parlamento", "europeu"]);
+        assert_eq!(word_segmenter.segment("OParlamento"), vec!["o", "parlamento"]);
+        assert_eq!(word_segmenter.segment_text("oparlamento  europeu"), "o parlamento europeu");
+    }
+
+    #[test]
+    fn the_2_grams_rescore_the_split() {
+        let word_freq = freq(&[("a", 100), ("b", 100), ("ab", 50)]);
+        assert_eq!(WordSegmenter::new(&word_freq, None).segment("ab"), vec!["ab"]);
+        let dic_2_grams = freq(&[("a b", 90)]);
+        assert_eq!(WordSegmenter::new(&word_freq, Some(&dic_2_grams)).segment("ab"), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn unknown_words_are_kept_whole() {
+        let word_segmenter = WordSegmenter::new(&word_freq(), None);
+        assert_eq!(word_segmenter.segment("xyz"), vec!["xyz"]);
+        assert_eq!(word_segmenter.segment("oxyz"), vec!["o", "xyz"]);
+        assert_eq!(word_segmenter.segment("parlamentoxyz"), vec!["parlamento", "xyz"]);
+    }
+
+    #[test]
+    fn empty_input() {
+        let word_segmenter = WordSegmenter::new(&word_freq(), None);
+        assert!(word_segmenter.segment("").is_empty());
+        assert_eq!(word_segmenter.segment_text(""), "");
+        assert_eq!(word_segmenter.segment_text("   "), "");
+        assert!(WordSegmenter::new(&HashMap::new(), None).segment("").is_empty());
+    }
+}